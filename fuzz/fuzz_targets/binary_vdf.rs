@@ -0,0 +1,18 @@
+#![no_main]
+
+use hbc::steam::binary_vdf::{self, DeserializerOptions, Utf8Policy, Value};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for token in binary_vdf::TokenDeserializer::new(data) {
+        if token.is_err() {
+            break;
+        }
+    }
+
+    let options = DeserializerOptions::new().utf8_policy(Utf8Policy::Bytes);
+
+    if let Ok(value) = options.from_bytes::<Value>(data) {
+        binary_vdf::to_bytes(&value).expect("a parsed value must serialize");
+    }
+});
@@ -0,0 +1,2 @@
+pub mod serde_utils;
+pub mod steam;
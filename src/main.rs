@@ -1,20 +1,18 @@
-use std::fs::{File, self};
-use std::io::BufReader;
+use std::fs;
 use std::path::PathBuf;
 
 use anyhow::{Result, Context};
-use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use steam::id::AppId;
-
-mod steam;
-mod serde_utils;
+use hbc::serde_utils::{deserialize_coerced, CaseInsensitive};
+use hbc::steam;
+use hbc::steam::binary_vdf::Value;
+use hbc::steam::convert::{convert, Format};
+use hbc::steam::id::AppId;
 
 fn main() -> Result<()> {
     let file = fs::read(get_shortcuts_vdf_path())?;
 
     let shortcuts = steam::binary_vdf::from_bytes::<ShortcutsVdf>(&file)?;
-    println!("{:#?}", shortcuts);
 
     let bytes = steam::binary_vdf::to_bytes(&shortcuts)
         .context("failed to serialize bvdf")?;
@@ -22,16 +20,11 @@ fn main() -> Result<()> {
 
     std::fs::write("/tmp/foo.bvdf", &bytes)?;
 
-    let shortcuts2 = steam::binary_vdf::from_bytes::<ShortcutsVdf>(&bytes)?;
-
-    // println!("{:#?}", shortcuts2);
-
-    // println!("{}", serde_json::to_string_pretty(&shortcuts).unwrap());
+    // Unlike the typed struct, the conversion keeps every key and value type as is
+    let json = convert(&file, Format::Binary, Format::Json)
+        .context("failed to convert shortcuts to JSON")?;
 
-    // for shortcut in shortcuts.get("shortcuts").unwrap().as_object().unwrap().values() {
-    //     let name = shortcut.get("AppName").unwrap();
-    //     println!("gameName: {}", name);
-    // }
+    println!("{}", String::from_utf8(json)?);
 
     Ok(())
 }
@@ -43,13 +36,15 @@ fn get_shortcuts_vdf_path() -> PathBuf {
 
 #[derive(Deserialize, Serialize, Debug)]
 struct ShortcutsVdf {
-    shortcuts: Vec<Shortcut>,
+    // Older Steam clients spell some keys differently
+    shortcuts: Vec<CaseInsensitive<Shortcut, Value>>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
 struct Shortcut {
-    #[serde(rename = "appid")]
+    // Third party shortcut managers don't always agree on the types of fields
+    #[serde(rename = "appid", deserialize_with = "deserialize_coerced")]
     app_id: AppId,
     app_name: String,
     exe: String,
@@ -58,21 +53,26 @@ struct Shortcut {
     icon: String,
     shortcut_path: String,
     launch_options: String,
+    #[serde(deserialize_with = "deserialize_coerced")]
     is_hidden: bool,
+    #[serde(deserialize_with = "deserialize_coerced")]
     allow_desktop_config: bool,
+    #[serde(deserialize_with = "deserialize_coerced")]
     allow_overlay: bool,
-    #[serde(rename = "openvr")]
-    open_vr: u32,
-    devkit: u32,
+    #[serde(rename = "openvr", default, deserialize_with = "deserialize_coerced")]
+    open_vr: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_coerced")]
+    devkit: Option<u32>,
     #[serde(rename = "DevkitGameID")]
-    devkit_game_id: String,
-    #[serde(rename = "DevkitOverrideAppID")]
-    devkit_override_app_id: u32,
-    last_play_time: u32,
+    devkit_game_id: Option<String>,
+    #[serde(rename = "DevkitOverrideAppID", default, deserialize_with = "deserialize_coerced")]
+    devkit_override_app_id: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_coerced")]
+    last_play_time: Option<u32>,
     #[serde(rename = "FlatpakAppID")]
-    flatpak_app_id: String,
+    flatpak_app_id: Option<String>,
+    #[serde(rename = "sortas")]
+    sort_as: Option<String>,
     #[serde(rename = "tags")]
     tags: Vec<String>,
-    #[serde(flatten)]
-    rest: IndexMap<String, serde_value::Value>,
 }
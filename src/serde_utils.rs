@@ -1,3 +1,9 @@
-
+mod case_insensitive;
+mod deserialize_coerced;
 mod deserialize_map_values;
+mod key_deserializer;
+pub mod primitive;
+pub use case_insensitive::CaseInsensitive;
+pub use deserialize_coerced::deserialize_coerced;
 pub use deserialize_map_values::deserialize_map_values;
+pub(crate) use key_deserializer::KeyDeserializer;
@@ -0,0 +1,527 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use indexmap::IndexMap;
+use serde::ser::{self, Impossible, SerializeMap};
+use serde::{de, forward_to_deserialize_any, Deserialize, Serialize};
+
+/// A struct whose field names are matched case-insensitively.
+///
+/// Keys that match a field of `T` only when ignoring ASCII case
+/// are written back with their original spelling,
+/// and keys that match no field at all are kept in `rest`.
+/// A key that matches a field exactly is never taken for another field,
+/// and input that spells the same field twice is rejected as a duplicate field.
+/// Entries of `rest` are written back right before the field they
+/// preceded when read, so that fields declared in the same order as
+/// in the input reproduce it exactly.
+///
+/// `T` must not contain a `#[serde(flatten)]` field,
+/// as serde does not reveal the field names of such structs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseInsensitive<T, V> {
+    pub value: T,
+    pub rest: IndexMap<String, V>,
+    // The original spelling of fields that were not spelled exactly like in `T`
+    spellings: Vec<(&'static str, String)>,
+    // The field that followed each key of `rest` in the input, if any
+    positions: Vec<(String, &'static str)>,
+}
+
+impl<T, V> CaseInsensitive<T, V> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            rest: IndexMap::new(),
+            spellings: Vec::new(),
+            positions: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// The spelling `field` is written with.
+    pub fn spelling(&self, field: &'static str) -> &str {
+        self.spellings
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map_or(field, |(_, spelling)| spelling)
+    }
+
+    /// The field that followed `key` of `rest` in the input.
+    fn position(&self, key: &str) -> Option<&'static str> {
+        self.positions
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|&(_, field)| field)
+    }
+}
+
+impl<T, V> From<T> for CaseInsensitive<T, V> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T, V> Deref for CaseInsensitive<T, V> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, V> DerefMut for CaseInsensitive<T, V> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<'de, T, V> Deserialize<'de> for CaseInsensitive<T, V>
+where
+    T: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut rest = IndexMap::new();
+        let mut spellings = Vec::new();
+        let mut positions = Vec::new();
+
+        let value = T::deserialize(Matcher {
+            deserializer,
+            rest: &mut rest,
+            spellings: &mut spellings,
+            positions: &mut positions,
+        })?;
+
+        Ok(Self {
+            value,
+            rest,
+            spellings,
+            positions,
+        })
+    }
+}
+
+impl<T, V> Serialize for CaseInsensitive<T, V>
+where
+    T: Serialize,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        self.value.serialize(Respeller {
+            serializer,
+            value: self,
+        })
+    }
+}
+
+struct Matcher<'a, D, R> {
+    deserializer: D,
+    rest: &'a mut IndexMap<String, R>,
+    spellings: &'a mut Vec<(&'static str, String)>,
+    positions: &'a mut Vec<(String, &'static str)>,
+}
+
+impl<'de, 'a, D, R> de::Deserializer<'de> for Matcher<'a, D, R>
+where
+    D: de::Deserializer<'de>,
+    R: Deserialize<'de>,
+{
+    type Error = D::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserializer.deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let visitor = MatchingVisitor {
+            visitor,
+            fields,
+            rest: self.rest,
+            spellings: self.spellings,
+            positions: self.positions,
+        };
+
+        self.deserializer.deserialize_struct(name, fields, visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.deserializer.is_human_readable()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+struct MatchingVisitor<'a, V, R> {
+    visitor: V,
+    fields: &'static [&'static str],
+    rest: &'a mut IndexMap<String, R>,
+    spellings: &'a mut Vec<(&'static str, String)>,
+    positions: &'a mut Vec<(String, &'static str)>,
+}
+
+impl<'de, 'a, V, R> de::Visitor<'de> for MatchingVisitor<'a, V, R>
+where
+    V: de::Visitor<'de>,
+    R: Deserialize<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        self.visitor.visit_map(MatchingMap {
+            map,
+            fields: self.fields,
+            rest: self.rest,
+            spellings: self.spellings,
+            positions: self.positions,
+            placed: 0,
+        })
+    }
+}
+
+struct MatchingMap<'a, A, R> {
+    map: A,
+    fields: &'static [&'static str],
+    rest: &'a mut IndexMap<String, R>,
+    spellings: &'a mut Vec<(&'static str, String)>,
+    positions: &'a mut Vec<(String, &'static str)>,
+    // The number of keys of `rest` that a field has followed
+    placed: usize,
+}
+
+impl<'a, A, R> MatchingMap<'a, A, R> {
+    fn find_field(&self, key: &str) -> Option<&'static str> {
+        let fields = self.fields.iter().copied();
+
+        fields
+            .clone()
+            .find(|&field| field == key)
+            .or_else(|| fields.clone().find(|field| field.eq_ignore_ascii_case(key)))
+    }
+}
+
+impl<'de, 'a, A, R> de::MapAccess<'de> for MatchingMap<'a, A, R>
+where
+    A: de::MapAccess<'de>,
+    R: Deserialize<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        while let Some(key) = self.map.next_key::<String>()? {
+            let field = match self.find_field(&key) {
+                Some(field) => field,
+                None => {
+                    let value = self.map.next_value()?;
+                    self.rest.insert(key, value);
+                    continue;
+                }
+            };
+
+            for key in self.rest.keys().skip(self.placed) {
+                self.positions.push((key.clone(), field));
+            }
+            self.placed = self.rest.len();
+
+            if field != key {
+                self.spellings.push((field, key));
+            }
+
+            let field = de::value::BorrowedStrDeserializer::new(field);
+
+            return seed.deserialize(field).map(Some);
+        }
+
+        Ok(None)
+    }
+
+    fn next_value_seed<S>(&mut self, seed: S) -> Result<S::Value, Self::Error>
+    where
+        S: de::DeserializeSeed<'de>,
+    {
+        self.map.next_value_seed(seed)
+    }
+}
+
+struct Respeller<'a, S, T, V> {
+    serializer: S,
+    value: &'a CaseInsensitive<T, V>,
+}
+
+impl<'a, S, T, V> ser::Serializer for Respeller<'a, S, T, V>
+where
+    S: ser::Serializer,
+    V: Serialize,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = Impossible<S::Ok, S::Error>;
+    type SerializeMap = Impossible<S::Ok, S::Error>;
+    type SerializeStruct = RespelledStruct<'a, S::SerializeMap, T, V>;
+    type SerializeStructVariant = Impossible<S::Ok, S::Error>;
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        let map = self
+            .serializer
+            .serialize_map(Some(len + self.value.rest.len()))?;
+
+        Ok(RespelledStruct {
+            map,
+            value: self.value,
+            written: 0,
+        })
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_char(self, _: char) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_str(self, _: &str) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_some<U>(self, _: &U) -> Result<Self::Ok, Self::Error>
+    where
+        U: ?Sized + Serialize,
+    {
+        Err(not_a_struct())
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_newtype_struct<U>(self, _: &'static str, _: &U) -> Result<Self::Ok, Self::Error>
+    where
+        U: ?Sized + Serialize,
+    {
+        Err(not_a_struct())
+    }
+
+    fn serialize_newtype_variant<U>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &U,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        U: ?Sized + Serialize,
+    {
+        Err(not_a_struct())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(not_a_struct())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.serializer.is_human_readable()
+    }
+}
+
+struct RespelledStruct<'a, M, T, V> {
+    map: M,
+    value: &'a CaseInsensitive<T, V>,
+    // The number of entries of `rest` written so far
+    written: usize,
+}
+
+impl<'a, M, T, V> RespelledStruct<'a, M, T, V>
+where
+    M: SerializeMap,
+    V: Serialize,
+{
+    /// Writes the entries of `rest` up to `end`.
+    fn write_rest(&mut self, end: usize) -> Result<(), M::Error> {
+        let rest = self.value.rest.iter().take(end).skip(self.written);
+
+        for (key, value) in rest {
+            self.map.serialize_entry(key, value)?;
+        }
+
+        self.written = self.written.max(end);
+
+        Ok(())
+    }
+}
+
+impl<'a, M, T, V> ser::SerializeStruct for RespelledStruct<'a, M, T, V>
+where
+    M: SerializeMap,
+    V: Serialize,
+{
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn serialize_field<U>(&mut self, key: &'static str, value: &U) -> Result<(), M::Error>
+    where
+        U: ?Sized + Serialize,
+    {
+        // Entries that preceded this field are written before it again
+        let preceding = self
+            .value
+            .rest
+            .keys()
+            .rposition(|name| self.value.position(name) == Some(key));
+
+        if let Some(index) = preceding {
+            self.write_rest(index + 1)?;
+        }
+
+        self.map.serialize_entry(self.value.spelling(key), value)
+    }
+
+    fn end(mut self) -> Result<M::Ok, M::Error> {
+        self.write_rest(self.value.rest.len())?;
+
+        self.map.end()
+    }
+}
+
+fn not_a_struct<E: ser::Error>() -> E {
+    E::custom("CaseInsensitive can only serialize structs")
+}
@@ -0,0 +1,128 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use serde::de::{self, IntoDeserializer};
+use serde::forward_to_deserialize_any;
+
+/// Deserializes a map key, which the vdf formats always store as a string.
+///
+/// Keys may still stand for other types, like the indices of a sparse
+/// sequence read into a `BTreeMap<u32, T>`, so numbers, bools and chars
+/// are parsed from the string when asked for.
+pub(crate) struct KeyDeserializer<'de, E> {
+    key: Cow<'de, str>,
+    human_readable: bool,
+    error: PhantomData<E>,
+}
+
+impl<'de, E> KeyDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    pub(crate) fn new(key: Cow<'de, str>, human_readable: bool) -> Self {
+        Self {
+            key,
+            human_readable,
+            error: PhantomData,
+        }
+    }
+
+    fn parse<T: FromStr>(&self, expected: &'static str) -> Result<T, E> {
+        self.key
+            .parse()
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(&self.key), &expected))
+    }
+}
+
+/// Implements `deserialize_*` methods for values parsed from keys.
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident($expected:literal),)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                visitor.$visit(self.parse($expected)?)
+            }
+        )*
+    };
+}
+
+impl<'de, E> de::Deserializer<'de> for KeyDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.key {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match &*self.key {
+            "0" | "false" => visitor.visit_bool(false),
+            "1" | "true" => visitor.visit_bool(true),
+            key => Err(de::Error::invalid_value(
+                de::Unexpected::Str(key),
+                &"a bool",
+            )),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8("an i8"),
+        deserialize_i16 => visit_i16("an i16"),
+        deserialize_i32 => visit_i32("an i32"),
+        deserialize_i64 => visit_i64("an i64"),
+        deserialize_i128 => visit_i128("an i128"),
+        deserialize_u8 => visit_u8("a u8"),
+        deserialize_u16 => visit_u16("a u16"),
+        deserialize_u32 => visit_u32("a u32"),
+        deserialize_u64 => visit_u64("a u64"),
+        deserialize_u128 => visit_u128("a u128"),
+        deserialize_char => visit_char("a char"),
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        // Only unit variants can be keys
+        visitor.visit_enum(self.key.into_deserializer())
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
+    }
+
+    forward_to_deserialize_any! {
+        f32 f64 str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
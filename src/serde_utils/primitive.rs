@@ -0,0 +1,53 @@
+//! (De)serializes enums as their integer discriminant, like `serde_repr`.
+//!
+//! Serde passes unit variants around by name and index only, so enums whose
+//! discriminants are fixed by a file format, e.g. the `OpenVR` field in
+//! shortcuts.vdf, can't be written as ints by serde's derives alone.
+//! Derive `enum_primitive_derive::Primitive` for the enum and use
+//! `#[serde(with = "hbc::serde_utils::primitive")]` on the field instead:
+//!
+//! ```
+//! use enum_primitive_derive::Primitive;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Primitive, Debug, PartialEq)]
+//! enum OpenVr {
+//!     Off = 0,
+//!     On = 1,
+//! }
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Shortcut {
+//!     #[serde(rename = "OpenVR", with = "hbc::serde_utils::primitive")]
+//!     open_vr: OpenVr,
+//! }
+//! ```
+
+use num_traits::{FromPrimitive, ToPrimitive};
+use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ToPrimitive,
+    S: Serializer,
+{
+    match value.to_u32() {
+        Some(value) => serializer.serialize_u32(value),
+        None => Err(ser::Error::custom("discriminant out of range for u32")),
+    }
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromPrimitive,
+    D: Deserializer<'de>,
+{
+    let value = u32::deserialize(deserializer)?;
+
+    T::from_u32(value).ok_or_else(|| {
+        de::Error::invalid_value(
+            de::Unexpected::Unsigned(value.into()),
+            &"a known discriminant",
+        )
+    })
+}
@@ -1,4 +1,8 @@
-
 pub mod text_vdf;
 pub mod binary_vdf;
-pub mod id;
\ No newline at end of file
+pub mod convert;
+pub mod kv3;
+pub mod appinfo;
+pub mod packageinfo;
+pub mod id;
+mod read_ext;
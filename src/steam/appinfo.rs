@@ -0,0 +1,174 @@
+use std::io::Read;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use serde::de::Deserialize;
+
+use super::binary_vdf::read::SliceRead;
+use super::binary_vdf::{self, TokenDeserializer};
+use super::id::AppId;
+use super::read_ext::ReadExt;
+
+const MAGIC_V27: u32 = 0x07564427;
+const MAGIC_V28: u32 = 0x07564428;
+const MAGIC_V29: u32 = 0x07564429;
+const APPS_END: u32 = 0;
+
+pub fn from_reader<R: Read>(mut reader: R) -> Result<AppInfoVdf> {
+    let mut bytes = Vec::new();
+
+    reader.read_to_end(&mut bytes)?;
+
+    from_bytes(&bytes)
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<AppInfoVdf> {
+    let mut reader = bytes;
+
+    let magic = reader.read_u32_le().context("failed to read magic")?;
+    let version = match magic {
+        MAGIC_V27 => 27,
+        MAGIC_V28 => 28,
+        MAGIC_V29 => 29,
+        _ => bail!("unknown appinfo magic: 0x{magic:08x}"),
+    };
+    let universe = reader.read_u32_le()?;
+
+    let key_table = if version >= 29 {
+        let offset = reader.read_u64_le()?;
+        let table = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| bytes.get(offset..))
+            .with_context(|| format!("string table offset out of range: {offset}"))?;
+
+        Some(read_key_table(table).context("failed to read string table")?)
+    } else {
+        None
+    };
+
+    let mut apps = Vec::new();
+
+    loop {
+        let app_id = reader.read_u32_le()?;
+
+        if app_id == APPS_END {
+            break;
+        }
+
+        let app = read_app(&mut reader, app_id, version, key_table.clone())
+            .with_context(|| format!("failed to read app {app_id}"))?;
+
+        apps.push(app);
+    }
+
+    Ok(AppInfoVdf {
+        version,
+        universe,
+        apps,
+    })
+}
+
+fn read_app(
+    reader: &mut &[u8],
+    app_id: u32,
+    version: u32,
+    key_table: Option<Arc<[String]>>,
+) -> Result<App> {
+    let size = reader.read_u32_le()?;
+
+    if size as usize > reader.len() {
+        bail!("entry size exceeds input: {size}");
+    }
+
+    let (mut entry, rest) = reader.split_at(size as usize);
+    *reader = rest;
+
+    let info_state = entry.read_u32_le()?;
+    let last_updated = entry.read_u32_le()?;
+    let pics_token = entry.read_u64_le()?;
+    let sha1 = entry.read_sha1()?;
+    let change_number = entry.read_u32_le()?;
+    let binary_sha1 = if version >= 28 {
+        Some(entry.read_sha1()?)
+    } else {
+        None
+    };
+
+    Ok(App {
+        app_id: AppId::from(app_id),
+        size,
+        info_state,
+        last_updated,
+        pics_token,
+        sha1,
+        change_number,
+        binary_sha1,
+        data: entry.to_vec(),
+        key_table,
+    })
+}
+
+fn read_key_table(mut table: &[u8]) -> Result<Arc<[String]>> {
+    let count = table.read_u32_le()?;
+    let mut keys = Vec::new();
+
+    for _ in 0..count {
+        let end = table
+            .iter()
+            .position(|&byte| byte == 0)
+            .context("unterminated string in string table")?;
+        let key = String::from_utf8(table[..end].to_vec())?;
+
+        keys.push(key);
+        table = &table[end + 1..];
+    }
+
+    Ok(keys.into())
+}
+
+#[derive(Debug)]
+pub struct AppInfoVdf {
+    pub version: u32,
+    pub universe: u32,
+    pub apps: Vec<App>,
+}
+
+#[derive(Debug)]
+pub struct App {
+    pub app_id: AppId,
+    pub size: u32,
+    pub info_state: u32,
+    pub last_updated: u32,
+    pub pics_token: u64,
+    pub sha1: [u8; 20],
+    pub change_number: u32,
+    pub binary_sha1: Option<[u8; 20]>,
+    data: Vec<u8>,
+    key_table: Option<Arc<[String]>>,
+}
+
+impl App {
+    /// The raw binary KV section of this app.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn tokens(&self) -> TokenDeserializer<SliceRead<'_>> {
+        let tokens = TokenDeserializer::from_slice(&self.data);
+
+        match &self.key_table {
+            Some(key_table) => tokens.with_key_table(key_table.clone()),
+            None => tokens,
+        }
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T> {
+        let value = binary_vdf::from_token_deserializer(self.tokens())
+            .with_context(|| format!("failed to deserialize app {}", *self.app_id))?;
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,181 @@
+use serde::Deserialize;
+
+use super::from_bytes;
+use crate::steam::binary_vdf::token_deserializer::Token;
+
+const KEYS: [&str; 3] = ["appinfo", "appid", "name"];
+const APPS: [(u32, &str); 2] = [(10, "Counter-Strike"), (20, "Team Fortress")];
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct AppData {
+    appinfo: AppInfo,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct AppInfo {
+    appid: u32,
+    name: String,
+}
+
+/// Builds an appinfo.vdf holding one app per `(app_id, name)`.
+fn appinfo(version: u32, apps: &[(u32, &str)]) -> Vec<u8> {
+    let magic = match version {
+        27 => 0x07564427u32,
+        28 => 0x07564428,
+        29 => 0x07564429,
+        _ => unreachable!(),
+    };
+
+    let mut bytes = Vec::new();
+    bytes.extend(magic.to_le_bytes());
+    bytes.extend(1u32.to_le_bytes());
+
+    if version >= 29 {
+        // Patched once the string table's offset is known
+        bytes.extend(0u64.to_le_bytes());
+    }
+
+    for &(app_id, name) in apps {
+        let mut entry = Vec::new();
+        entry.extend(2u32.to_le_bytes());
+        entry.extend(1_700_000_000u32.to_le_bytes());
+        entry.extend(0x1122334455u64.to_le_bytes());
+        entry.extend([app_id as u8; 20]);
+        entry.extend((app_id + 1000).to_le_bytes());
+
+        if version >= 28 {
+            entry.extend([0xb1; 20]);
+        }
+
+        entry.extend(app_data(version, app_id, name));
+
+        bytes.extend(app_id.to_le_bytes());
+        bytes.extend((entry.len() as u32).to_le_bytes());
+        bytes.extend(entry);
+    }
+
+    bytes.extend(0u32.to_le_bytes());
+
+    if version >= 29 {
+        let offset = bytes.len() as u64;
+        bytes[8..16].copy_from_slice(&offset.to_le_bytes());
+
+        bytes.extend((KEYS.len() as u32).to_le_bytes());
+
+        for key in KEYS {
+            bytes.extend(key.as_bytes());
+            bytes.push(0x00);
+        }
+    }
+
+    bytes
+}
+
+/// `appinfo { appid = app_id, name = name }`, with v29 keys as indices into `KEYS`.
+fn app_data(version: u32, app_id: u32, name: &str) -> Vec<u8> {
+    let key = |bytes: &mut Vec<u8>, value_type: u8, key: &str| {
+        bytes.push(value_type);
+
+        if version >= 29 {
+            let index = KEYS.iter().position(|k| *k == key).unwrap() as u32;
+            bytes.extend(index.to_le_bytes());
+        } else {
+            bytes.extend(key.as_bytes());
+            bytes.push(0x00);
+        }
+    };
+
+    let mut bytes = Vec::new();
+    key(&mut bytes, 0x00, "appinfo");
+    key(&mut bytes, 0x02, "appid");
+    bytes.extend(app_id.to_le_bytes());
+    key(&mut bytes, 0x01, "name");
+    bytes.extend(name.as_bytes());
+    bytes.push(0x00);
+    bytes.extend([0x08, 0x08]);
+    bytes
+}
+
+fn assert_apps(version: u32) {
+    let bytes = appinfo(version, &APPS);
+    let appinfo = from_bytes(&bytes).unwrap();
+
+    assert_eq!(appinfo.version, version);
+    assert_eq!(appinfo.universe, 1);
+    assert_eq!(appinfo.apps.len(), 2);
+
+    let app = &appinfo.apps[1];
+
+    assert_eq!(*app.app_id, 20);
+    assert_eq!(app.info_state, 2);
+    assert_eq!(app.last_updated, 1_700_000_000);
+    assert_eq!(app.pics_token, 0x1122334455);
+    assert_eq!(app.sha1, [20; 20]);
+    assert_eq!(app.change_number, 1020);
+
+    if version >= 28 {
+        assert_eq!(app.binary_sha1, Some([0xb1; 20]));
+    } else {
+        assert_eq!(app.binary_sha1, None);
+    }
+
+    for (app, (appid, name)) in appinfo.apps.iter().zip(APPS) {
+        assert_eq!(
+            app.deserialize::<AppData>().unwrap(),
+            AppData {
+                appinfo: AppInfo {
+                    appid,
+                    name: name.to_string(),
+                },
+            }
+        );
+    }
+}
+
+#[test]
+fn v27() {
+    assert_apps(27);
+}
+
+#[test]
+fn v28() {
+    assert_apps(28);
+}
+
+#[test]
+fn v29() {
+    assert_apps(29);
+
+    let bytes = appinfo(29, &APPS[..1]);
+    let appinfo = from_bytes(&bytes).unwrap();
+    let app = &appinfo.apps[0];
+
+    // Keys are only stored in the string table at the end of the file
+    assert!(!app.data().windows(5).any(|window| window == b"appid"));
+
+    let keys = app
+        .tokens()
+        .filter_map(|token| match token.unwrap() {
+            Token::Key(key) => Some(key.into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(keys, ["appinfo", "appid", "name"]);
+}
+
+#[test]
+fn errors() {
+    let bytes = appinfo(28, &APPS[..1]);
+
+    // Cut off within the app's entry
+    let err = from_bytes(&bytes[..bytes.len() - 10]).unwrap_err();
+    assert_eq!(err.to_string(), "failed to read app 10");
+    assert!(format!("{err:#}").contains("entry size exceeds input"));
+
+    // Cut off before the end marker
+    assert!(from_bytes(&bytes[..bytes.len() - 2]).is_err());
+
+    let err = from_bytes(&[0x27, 0x44, 0x56, 0x08]).unwrap_err();
+    assert_eq!(err.to_string(), "unknown appinfo magic: 0x08564427");
+}
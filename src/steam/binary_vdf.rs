@@ -1,23 +1,81 @@
 use enum_primitive_derive::Primitive;
 
 pub use self::{
-    de::{from_bytes, from_reader},
+    de::{from_bytes, from_reader, from_token_deserializer},
+    error::{Error, ErrorKind, Result},
+    object::Object,
+    options::{DeserializerOptions, SequencePolicy},
     ser::{to_bytes, to_writer},
-    token_deserializer::TokenDeserializer,
+    token_deserializer::{TokenDeserializer, Utf8Policy},
     token_serializer::TokenSerializer,
+    value::{from_value, to_value, Value},
 };
 
+#[cfg(feature = "tokio")]
+pub use self::{
+    async_token_deserializer::{from_async_reader, AsyncTokenDeserializer},
+    async_token_serializer::{to_async_writer, AsyncTokenSerializer},
+};
+
+#[cfg(feature = "tokio")]
+mod async_token_deserializer;
+#[cfg(feature = "tokio")]
+mod async_token_serializer;
 mod de;
+mod error;
+mod object;
+mod options;
+pub mod read;
 mod ser;
 pub mod token_deserializer;
 mod token_serializer;
+mod value;
+
+#[cfg(test)]
+mod tests;
 
 const STRING_END: u8 = 0x00;
 const OBJECT_END: u8 = 0x08;
+const OBJECT_END_ALT: u8 = 0x0B;
+
+/// The byte that ends an object.
+///
+/// Most documents only use `Regular`, but some end their objects with `Alternate`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EndMarker {
+    /// 0x08
+    #[default]
+    Regular,
+    /// 0x0B
+    Alternate,
+}
+
+impl EndMarker {
+    fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            OBJECT_END => Some(EndMarker::Regular),
+            OBJECT_END_ALT => Some(EndMarker::Alternate),
+            _ => None,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            EndMarker::Regular => OBJECT_END,
+            EndMarker::Alternate => OBJECT_END_ALT,
+        }
+    }
+}
 
 #[derive(Primitive, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum ValueType {
     Object = 0x00,
     String = 0x01,
     Int = 0x02,
+    Float = 0x03,
+    Pointer = 0x04,
+    WideString = 0x05,
+    Color = 0x06,
+    UInt64 = 0x07,
+    Int64 = 0x0A,
 }
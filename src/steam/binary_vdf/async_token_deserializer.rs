@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+
+use num_traits::FromPrimitive;
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+use super::error::Result;
+use super::token_deserializer::{NextOp, Token};
+use super::{DeserializerOptions, TokenDeserializer, Utf8Policy};
+use super::{EndMarker, ValueType, STRING_END};
+
+/// Deserializes a document from an async reader.
+///
+/// Like `from_reader` this stops right after the document. Its bytes are
+/// held in memory in full before they are deserialized; use
+/// `AsyncTokenDeserializer` to process the document without buffering it.
+pub async fn from_async_reader<D, R>(reader: R) -> Result<D>
+where
+    D: DeserializeOwned,
+    R: AsyncBufRead + Unpin,
+{
+    DeserializerOptions::default().from_async_reader(reader).await
+}
+
+/// The async counterpart of `TokenDeserializer`.
+///
+/// Each token is read from the async reader in full,
+/// and then parsed by a regular `TokenDeserializer`.
+pub struct AsyncTokenDeserializer<R> {
+    reader: R,
+    tokens: TokenDeserializer<VecDeque<u8>>,
+}
+
+impl<R> AsyncTokenDeserializer<R>
+where
+    R: AsyncBufRead + Unpin,
+{
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            tokens: TokenDeserializer::new(VecDeque::new()),
+        }
+    }
+
+    pub fn with_options(mut self, options: DeserializerOptions) -> Self {
+        self.tokens = self.tokens.with_options(options);
+        self
+    }
+
+    pub fn with_utf8_policy(mut self, utf8_policy: Utf8Policy) -> Self {
+        self.tokens = self.tokens.with_utf8_policy(utf8_policy);
+        self
+    }
+
+    /// See `TokenDeserializer::depth`.
+    pub fn depth(&self) -> usize {
+        self.tokens.depth()
+    }
+
+    /// See `TokenDeserializer::path`.
+    pub fn path(&self) -> &[String] {
+        self.tokens.path()
+    }
+
+    /// See `TokenDeserializer::position`.
+    pub fn position(&self) -> u64 {
+        self.tokens.position()
+    }
+
+    pub async fn next(&mut self) -> Option<Result<Token<'static>>> {
+        if let Err(err) = self.fill().await {
+            return Some(Err(self.tokens.fail(err)));
+        }
+
+        self.tokens.next()
+    }
+
+    /// See `TokenDeserializer::skip_value`.
+    ///
+    /// The skipped value is read into memory in full, but none of it is decoded.
+    pub async fn skip_value(&mut self) -> Result<()> {
+        if let Err(err) = self.fill_value().await {
+            return Err(self.tokens.fail(err));
+        }
+
+        self.tokens.skip_value()
+    }
+
+    /// Reads the rest of the document into memory, to be parsed without awaiting.
+    pub(super) async fn into_buffered(mut self) -> Result<TokenDeserializer<VecDeque<u8>>> {
+        if let Err(err) = self.fill_value().await {
+            return Err(self.tokens.fail(err));
+        }
+
+        Ok(self.tokens)
+    }
+
+    /// Reads the next token from the async reader into the parser's buffer.
+    async fn fill(&mut self) -> Result<()> {
+        let max_string_len = self.tokens.options().max_string_len;
+        let mut buf = Vec::new();
+
+        let value_type = match self.tokens.next_op() {
+            NextOp::DoNothing => return Ok(()),
+            NextOp::ParseValue(value_type) => value_type,
+            NextOp::ParseKey => {
+                let byte = self.reader.read_u8().await?;
+
+                buf.push(byte);
+
+                // Ends of objects and unknown types are left to the parser
+                if ValueType::from_u8(byte).is_some() {
+                    self.read_string(&mut buf, max_string_len).await?;
+                }
+
+                self.push(buf);
+
+                return Ok(());
+            }
+        };
+
+        match value_type {
+            ValueType::Object => {}
+            ValueType::String => {
+                self.read_string(&mut buf, max_string_len).await?;
+            }
+            ValueType::WideString => {
+                self.read_wide_string(&mut buf, max_string_len).await?;
+            }
+            ValueType::Int | ValueType::Float | ValueType::Pointer | ValueType::Color => {
+                buf.extend(self.reader.read_u32_le().await?.to_le_bytes());
+            }
+            ValueType::UInt64 | ValueType::Int64 => {
+                buf.extend(self.reader.read_u64_le().await?.to_le_bytes());
+            }
+        }
+
+        self.push(buf);
+
+        Ok(())
+    }
+
+    /// Reads the raw bytes of the value of the most recent key into the parser's buffer,
+    /// all the way to the end of the value if it is an object.
+    ///
+    /// Reading stops early at anything the parser rejects, such as an unknown
+    /// value type or the end of the input, leaving it to the parser to report.
+    async fn fill_value(&mut self) -> Result<()> {
+        let value_type = match self.tokens.next_op() {
+            NextOp::ParseValue(value_type) => value_type,
+            NextOp::ParseKey | NextOp::DoNothing => return Ok(()),
+        };
+
+        let mut buf = Vec::new();
+        let result = self.read_value(&mut buf, value_type).await;
+
+        self.push(buf);
+
+        result
+    }
+
+    async fn read_value(&mut self, buf: &mut Vec<u8>, mut value_type: ValueType) -> Result<()> {
+        let max_string_len = self.tokens.options().max_string_len;
+        let max_depth = self.tokens.options().max_depth;
+
+        // Objects opened within the value
+        let mut depth = 0;
+
+        loop {
+            let complete = match value_type {
+                ValueType::Object => {
+                    if self.tokens.depth() + depth >= max_depth {
+                        return Ok(());
+                    }
+
+                    depth += 1;
+
+                    true
+                }
+                ValueType::String => self.read_string(buf, max_string_len).await?,
+                ValueType::WideString => self.read_wide_string(buf, max_string_len).await?,
+                ValueType::Int | ValueType::Float | ValueType::Pointer | ValueType::Color => {
+                    self.read_bytes(buf, 4).await?
+                }
+                ValueType::UInt64 | ValueType::Int64 => self.read_bytes(buf, 8).await?,
+            };
+
+            if !complete {
+                return Ok(());
+            }
+
+            value_type = loop {
+                if depth == 0 || !self.read_bytes(buf, 1).await? {
+                    return Ok(());
+                }
+
+                let byte = buf[buf.len() - 1];
+
+                if EndMarker::from_u8(byte).is_some() {
+                    depth -= 1;
+                    continue;
+                }
+
+                match ValueType::from_u8(byte) {
+                    Some(value_type) => break value_type,
+                    None => return Ok(()),
+                }
+            };
+
+            if !self.read_string(buf, max_string_len).await? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads a string up to and including its terminator,
+    /// returning whether it was found within the limit.
+    async fn read_string(&mut self, buf: &mut Vec<u8>, max_len: usize) -> Result<bool> {
+        // Allow for one more byte than the limit to find the terminator
+        let limit = (max_len as u64).saturating_add(1);
+
+        // Running out of input is left to the parser
+        (&mut self.reader)
+            .take(limit)
+            .read_until(STRING_END, buf)
+            .await?;
+
+        Ok(buf.last() == Some(&STRING_END))
+    }
+
+    async fn read_wide_string(&mut self, buf: &mut Vec<u8>, max_len: usize) -> Result<bool> {
+        let start = buf.len();
+
+        loop {
+            if !self.read_bytes(buf, 2).await? {
+                return Ok(false);
+            }
+
+            if buf[buf.len() - 2..] == [0, 0] {
+                return Ok(true);
+            }
+
+            // Allow for one more unit than the limit to find the terminator
+            if buf.len() - start > max_len.saturating_add(2) {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Reads `len` bytes, or as many as are left, returning whether there were enough.
+    async fn read_bytes(&mut self, buf: &mut Vec<u8>, len: u64) -> Result<bool> {
+        let read = (&mut self.reader).take(len).read_to_end(buf).await?;
+
+        Ok(read as u64 == len)
+    }
+
+    fn push(&mut self, buf: Vec<u8>) {
+        self.tokens.get_mut().extend(buf);
+    }
+}
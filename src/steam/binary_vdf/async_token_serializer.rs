@@ -0,0 +1,129 @@
+use serde::Serialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::error::Result;
+use super::{to_bytes, EndMarker, TokenSerializer};
+
+/// Serializes a document to an async writer and flushes it.
+///
+/// The document is buffered in memory before it is written.
+pub async fn to_async_writer<S, W>(mut writer: W, value: &S) -> Result<()>
+where
+    S: Serialize,
+    W: AsyncWrite + Unpin,
+{
+    let bytes = to_bytes(value)?;
+
+    writer.write_all(&bytes).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// The async counterpart of `TokenSerializer`.
+///
+/// Each token is encoded by a regular `TokenSerializer`
+/// and then written to the async writer.
+pub struct AsyncTokenSerializer<W> {
+    writer: W,
+    tokens: TokenSerializer<Vec<u8>>,
+}
+
+impl<W> AsyncTokenSerializer<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            tokens: TokenSerializer::new(Vec::new()),
+        }
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await?;
+
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub async fn emit_object_start(&mut self) -> Result<()> {
+        self.tokens.emit_object_start()?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_key(&mut self, key: String) -> Result<()> {
+        self.tokens.emit_key(key)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_object_end(&mut self, end: EndMarker) -> Result<()> {
+        self.tokens.emit_object_end(end)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_string(&mut self, string: &str) -> Result<()> {
+        self.tokens.emit_string(string)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_int(&mut self, value: u32) -> Result<()> {
+        self.tokens.emit_int(value)?;
+        self.write_buffered().await
+    }
+
+    /// See `TokenSerializer::emit_none`.
+    pub async fn emit_none(&mut self) -> Result<()> {
+        self.tokens.emit_none()?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.tokens.emit_bytes(bytes)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_float(&mut self, value: f32) -> Result<()> {
+        self.tokens.emit_float(value)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_pointer(&mut self, value: u32) -> Result<()> {
+        self.tokens.emit_pointer(value)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_wide_string(&mut self, string: &str) -> Result<()> {
+        self.tokens.emit_wide_string(string)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_color(&mut self, value: u32) -> Result<()> {
+        self.tokens.emit_color(value)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_uint64(&mut self, value: u64) -> Result<()> {
+        self.tokens.emit_uint64(value)?;
+        self.write_buffered().await
+    }
+
+    pub async fn emit_int64(&mut self, value: i64) -> Result<()> {
+        self.tokens.emit_int64(value)?;
+        self.write_buffered().await
+    }
+
+    async fn write_buffered(&mut self) -> Result<()> {
+        let buf = self.tokens.get_mut();
+
+        if !buf.is_empty() {
+            self.writer.write_all(buf).await?;
+            buf.clear();
+        }
+
+        Ok(())
+    }
+}
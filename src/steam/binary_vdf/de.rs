@@ -1,95 +1,348 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::io::BufRead;
+use std::iter::{self, Once};
+use std::mem;
+use std::str::FromStr;
 
-use anyhow::{Context, Result};
-use serde::de::{self, DeserializeOwned, MapAccess, SeqAccess, IntoDeserializer};
+use serde::de::value::{BorrowedStrDeserializer, MapDeserializer};
+use serde::de::{
+    self, Deserialize, DeserializeOwned, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess,
+};
 use serde::{forward_to_deserialize_any};
 
+use super::error::{Error, ErrorKind, Result};
+use super::read::Read;
 use super::token_deserializer::Token;
-use super::TokenDeserializer;
+use super::value::{
+    COLOR_TOKEN, OBJECT_END_ALT_TOKEN, POINTER_TOKEN, VALUE_TOKEN, WIDE_STRING_TOKEN,
+};
+use super::{EndMarker, SequencePolicy, TokenDeserializer};
+use crate::serde_utils::KeyDeserializer;
 
-pub fn from_reader<D: DeserializeOwned, R: BufRead>(reader: R) -> Result<D, de::value::Error> {
+pub fn from_reader<D: DeserializeOwned, R: BufRead>(reader: R) -> Result<D> {
+    from_token_deserializer(TokenDeserializer::new(reader))
+}
+
+pub fn from_token_deserializer<'de, D: Deserialize<'de>, R: Read<'de>>(
+    token_deserializer: TokenDeserializer<R>,
+) -> Result<D> {
     let mut de = Deserializer {
-        token_deserializer: TokenDeserializer::new(reader),
+        token_deserializer,
+        replay: VecDeque::new(),
+        replayed: None,
+        buffered: false,
     };
 
-    D::deserialize(&mut de)
+    D::deserialize(&mut de).map_err(|err| de.locate(err))
 }
 
-pub fn from_bytes<D: DeserializeOwned>(bytes: &[u8]) -> Result<D, de::value::Error> {
-    from_reader(bytes)
+/// Deserializes from memory.
+///
+/// Unlike `from_reader` this can borrow from `bytes`,
+/// e.g. into `&str` or `Cow<str>` fields.
+pub fn from_bytes<'de, D: Deserialize<'de>>(bytes: &'de [u8]) -> Result<D> {
+    from_token_deserializer(TokenDeserializer::from_slice(bytes))
 }
 
-struct Deserializer<R> {
+struct Deserializer<'de, R> {
     token_deserializer: TokenDeserializer<R>,
+    // Tokens that were read ahead, to be handed out before any others
+    replay: VecDeque<(Token<'de>, Location)>,
+    // Where the most recent token was read from, if it was read ahead
+    replayed: Option<Location>,
+    // Set while reading the values of a map, which serde buffers without knowing
+    // their type for `#[serde(flatten)]`, so `deserialize_any` keeps them intact
+    buffered: bool,
 }
 
-impl<R> Deserializer<R>
+#[derive(Clone)]
+struct Location {
+    offset: u64,
+    path: Vec<String>,
+}
+
+impl<'de, R> Deserializer<'de, R>
 where
-    R: BufRead,
+    R: Read<'de>,
 {
-    fn expect_token(&mut self) -> Result<Token, de::value::Error> {
-        let token = self.token_deserializer.next().context("no more data to deserialize")
-            .map_err(de::Error::custom)?
-            .map_err(de::Error::custom)?;
+    fn expect_token(&mut self) -> Result<Token<'de>> {
+        if let Some((token, location)) = self.replay.pop_front() {
+            self.replayed = Some(location);
+            return Ok(token);
+        }
 
-        Ok(token)
-    }
+        self.replayed = None;
 
-    fn expect_int(&mut self) -> Result<u32, de::value::Error> {
-        match self.expect_token()? {
-            Token::Int(value) => Ok(value),
-            token => unexpected_token(&token),
+        match self.token_deserializer.next_token() {
+            Some(token) => token,
+            None => Err(Error::new(ErrorKind::UnexpectedEof)),
         }
     }
 
-    fn expect_object_start(&mut self) -> Result<(), de::value::Error> {
+    fn expect_object_start(&mut self) -> Result<()> {
         match self.expect_token()? {
             Token::ObjectStart => Ok(()),
-            token => unexpected_token(&token),
+            token => unexpected_token("object", token),
         }
     }
 
-    fn expect_key_or_object_end(&mut self) -> Result<Option<String>, de::value::Error> {
+    fn expect_key_or_object_end(&mut self) -> Result<Option<Cow<'de, str>>> {
         match self.expect_token()? {
             Token::Key(key) => Ok(Some(key)),
-            Token::ObjectEnd => Ok(None),
-            token => unexpected_token(&token),
+            Token::ObjectEnd(_) => Ok(None),
+            token => unexpected_token("key or end of object", token),
+        }
+    }
+
+    /// The location of the most recent token.
+    fn location(&self) -> Location {
+        match &self.replayed {
+            Some(location) => location.clone(),
+            None => Location {
+                offset: self.token_deserializer.token_offset(),
+                path: self.token_deserializer.path().to_vec(),
+            },
+        }
+    }
+
+    /// Attaches the location of the most recent token to `err`.
+    fn locate(&self, err: Error) -> Error {
+        match &self.replayed {
+            Some(location) => err.at(location.offset, &location.path),
+            None => self.token_deserializer.locate(err),
+        }
+    }
+
+    fn sequence_policy(&self) -> SequencePolicy {
+        self.token_deserializer.options().sequence_policy
+    }
+
+    fn coerce_types(&self) -> bool {
+        self.token_deserializer.options().coerce_types
+    }
+
+    /// Reads the remaining entries of a sequence ahead, starting with the one at `key`,
+    /// and queues them up again ordered by their keys.
+    fn sort_sequence(&mut self, key: Cow<'de, str>, next_index: u32) -> Result<()> {
+        let mut entries = Vec::new();
+        let mut next_key = Some(key);
+        let mut location = self.location();
+
+        while let Some(key) = next_key {
+            let index = match key.parse::<u32>() {
+                Ok(index) if index >= next_index => index,
+                // All indices before `next_index` have been used up already
+                Ok(index) => return Err(duplicate_index(index)),
+                Err(_) => return Err(invalid_index(&key)),
+            };
+            let mut tokens = vec![(Token::Key(key), location)];
+
+            self.read_value_tokens(&mut tokens)?;
+            entries.push((index, tokens));
+
+            next_key = self.expect_key_or_object_end()?;
+            location = self.location();
+        }
+
+        entries.sort_by_key(|&(index, _)| index);
+
+        if let Some(entries) = entries.windows(2).find(|entries| entries[0].0 == entries[1].0) {
+            let (index, tokens) = &entries[1];
+            let location = &tokens[0].1;
+
+            return Err(duplicate_index(*index).at(location.offset, &location.path));
+        }
+
+        let mut sorted = entries
+            .into_iter()
+            .flat_map(|(_, tokens)| tokens)
+            .collect::<VecDeque<_>>();
+
+        // Anything queued already comes after this object.
+        // Sequences don't keep their end marker, so the regular one will do.
+        sorted.push_back((Token::ObjectEnd(EndMarker::Regular), location));
+        sorted.append(&mut self.replay);
+        self.replay = sorted;
+
+        Ok(())
+    }
+
+    fn read_value_tokens(&mut self, tokens: &mut Vec<(Token<'de>, Location)>) -> Result<()> {
+        let mut depth = 0;
+
+        loop {
+            let token = self.expect_token()?;
+
+            match token {
+                Token::ObjectStart => depth += 1,
+                Token::ObjectEnd(_) => depth -= 1,
+                _ => {}
+            }
+
+            tokens.push((token, self.location()));
+
+            if depth == 0 {
+                return Ok(());
+            }
         }
     }
+
+    fn deserialize_token<V>(&mut self, token: Token<'de>, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match token {
+            Token::ObjectStart => visitor.visit_map(self),
+            Token::Int(value) => visitor.visit_u32(value),
+            Token::String(Cow::Borrowed(value)) => visitor.visit_borrowed_str(value),
+            Token::String(Cow::Owned(value)) => visitor.visit_string(value),
+            Token::Float(value) => visitor.visit_f32(value),
+            Token::Pointer(value) => visitor.visit_u32(value),
+            Token::WideString(value) => visitor.visit_string(value),
+            Token::Color(value) => visitor.visit_u32(value),
+            Token::UInt64(value) => visitor.visit_u64(value),
+            Token::Int64(value) => visitor.visit_i64(value),
+            Token::Bytes(Cow::Borrowed(value)) => visitor.visit_borrowed_bytes(value),
+            Token::Bytes(Cow::Owned(value)) => visitor.visit_byte_buf(value),
+            token => unexpected_token("value", token),
+        }
+    }
+
+    fn deserialize_value<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_token()? {
+            Token::Pointer(value) => visitor.visit_map(typed_value(POINTER_TOKEN, value)),
+            Token::WideString(value) => {
+                visitor.visit_map(typed_value(WIDE_STRING_TOKEN, value))
+            }
+            Token::Color(value) => visitor.visit_map(typed_value(COLOR_TOKEN, value)),
+            Token::ObjectStart => visitor.visit_map(ValueObject { de: self, end: false }),
+            token => self.deserialize_token(token, visitor),
+        }
+    }
+}
+
+fn typed_value<'de, T>(
+    name: &'static str,
+    value: T,
+) -> MapDeserializer<'de, Once<(&'static str, T)>, Error>
+where
+    T: IntoDeserializer<'de, Error>,
+{
+    MapDeserializer::new(iter::once((name, value)))
+}
+
+fn unexpected_token<T>(expected: &'static str, token: Token) -> Result<T> {
+    Err(Error::unexpected_token(expected, token.into_owned()))
+}
+
+fn invalid_index(key: &str) -> Error {
+    de::Error::invalid_value(de::Unexpected::Str(key), &"a sequence index")
+}
+
+fn duplicate_index(index: u32) -> Error {
+    de::Error::custom(format!("duplicate sequence index {index}"))
 }
 
-fn unexpected_token<T>(token: &Token) -> Result<T, de::value::Error> {
-    Err(de::Error::custom(format!("unexpected token: {:?}", token)))
+fn parse_number<T: FromStr>(value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(value), &"a number"))
 }
 
-impl<'de, R> serde::Deserializer<'de> for &mut Deserializer<R>
+/// Implements numeric `deserialize_*` methods, which also accept
+/// numbers stored as strings if `DeserializerOptions::coerce_types` is set.
+macro_rules! deserialize_coerced {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                match self.expect_token()? {
+                    Token::String(value) if self.coerce_types() => {
+                        visitor.$visit(parse_number(&value)?)
+                    }
+                    token => self.deserialize_token(token, visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de, R> serde::Deserializer<'de> for &mut Deserializer<'de, R>
 where
-    R: BufRead,
+    R: Read<'de>,
 {
-    type Error = de::value::Error;
+    type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        let value = match self.expect_token()? {
-            Token::ObjectStart => visitor.visit_map(self)?,
-            Token::Int(value) => visitor.visit_u32(value)?,
-            Token::String(value) => visitor.visit_string(value)?,
-            token => return unexpected_token(&token),
-        };
+        if self.buffered {
+            return self.deserialize_value(visitor);
+        }
 
-        Ok(value)
+        let token = self.expect_token()?;
+
+        self.deserialize_token(token, visitor)
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        match self.expect_int()? {
+        let value = match self.expect_token()? {
+            Token::Int(value) => value,
+            Token::String(value) if self.coerce_types() => match &*value {
+                "0" | "false" => 0,
+                "1" | "true" => 1,
+                _ => return Err(de::Error::invalid_value(de::Unexpected::Str(&value), &"a bool")),
+            },
+            token => return unexpected_token("int", token),
+        };
+
+        match value {
             0 => visitor.visit_bool(false),
             1 => visitor.visit_bool(true),
-            value => return Err(de::Error::custom(format!("invalid bool value: {}", value))),
+            value => Err(de::Error::custom(format!("invalid bool value: {}", value))),
+        }
+    }
+
+    deserialize_coerced! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_token()? {
+            Token::Int(value) if self.coerce_types() => visitor.visit_string(value.to_string()),
+            Token::UInt64(value) if self.coerce_types() => visitor.visit_string(value.to_string()),
+            Token::Int64(value) if self.coerce_types() => visitor.visit_string(value.to_string()),
+            token => self.deserialize_token(token, visitor),
         }
     }
 
@@ -98,7 +351,150 @@ where
         V: de::Visitor<'de>,
     {
         self.expect_object_start()?;
-        visitor.visit_seq(self)
+
+        let mut seq = Seq {
+            de: &mut *self,
+            index: 0,
+            sorted: false,
+            end: false,
+        };
+        let value = visitor.visit_seq(&mut seq)?;
+
+        // Fixed size sequences like tuples stop reading after their last element
+        if !seq.end {
+            if let Some(key) = self.expect_key_or_object_end()? {
+                return unexpected_token("end of object", Token::Key(key));
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_token()? {
+            Token::String(Cow::Borrowed(value)) => visitor.visit_borrowed_bytes(value.as_bytes()),
+            Token::String(Cow::Owned(value)) => visitor.visit_byte_buf(value.into_bytes()),
+            Token::Bytes(Cow::Borrowed(value)) => visitor.visit_borrowed_bytes(value),
+            Token::Bytes(Cow::Owned(value)) => visitor.visit_byte_buf(value),
+            token => unexpected_token("string", token),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        // Absent values have no representation, so any value that is present is `Some`.
+        // Missing fields are taken care of by serde.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name == VALUE_TOKEN {
+            return self.deserialize_value(visitor);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let token = self.expect_token()?;
+
+        let buffered = mem::replace(&mut self.buffered, true);
+        let value = self.deserialize_token(token, visitor);
+        self.buffered = buffered;
+
+        value
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let token = self.expect_token()?;
+
+        let buffered = mem::replace(&mut self.buffered, false);
+        let value = self.deserialize_token(token, visitor);
+        self.buffered = buffered;
+
+        value
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_token()? {
+            Token::String(Cow::Borrowed(variant)) => {
+                visitor.visit_enum(BorrowedStrDeserializer::<Error>::new(variant))
+            }
+            Token::String(Cow::Owned(variant)) => {
+                visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant))
+            }
+            // Steam stores some enumerations as plain ints, those map to the variant index.
+            // Enums with explicit discriminants need `serde_utils::primitive` instead.
+            Token::Int(variant_index) => {
+                visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant_index))
+            }
+            Token::ObjectStart => {
+                let value = visitor.visit_enum(&mut *self)?;
+
+                match self.expect_key_or_object_end()? {
+                    None => Ok(value),
+                    Some(key) => unexpected_token("end of object", Token::Key(key)),
+                }
+            }
+            token => unexpected_token("enum", token),
+        }
     }
 
     fn is_human_readable(&self) -> bool {
@@ -106,17 +502,15 @@ where
     }
 
     forward_to_deserialize_any! {
-        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct tuple
-        tuple_struct map struct enum identifier ignored_any
+        i128 u128 char unit unit_struct identifier ignored_any
     }
 }
 
-impl<'de, R> MapAccess<'de> for Deserializer<R>
+impl<'de, R> MapAccess<'de> for Deserializer<'de, R>
 where
-    R: BufRead,
+    R: Read<'de>,
 {
-    type Error = de::value::Error;
+    type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
     where
@@ -127,7 +521,7 @@ where
             None => return Ok(None),
         };
 
-        let key = seed.deserialize(key.into_deserializer())?;
+        let key = seed.deserialize(KeyDeserializer::<Error>::new(key, false))?;
 
         Ok(Some(key))
     }
@@ -140,20 +534,156 @@ where
     }
 }
 
-impl<'de, R> SeqAccess<'de> for Deserializer<R>
+/// The entries of an object read into a `Value`, followed by
+/// an `OBJECT_END_ALT_TOKEN` entry if the object ends with that marker.
+struct ValueObject<'a, 'de, R> {
+    de: &'a mut Deserializer<'de, R>,
+    end: bool,
+}
+
+impl<'de, 'a, R> MapAccess<'de> for ValueObject<'a, 'de, R>
 where
-    R: BufRead,
+    R: Read<'de>,
 {
-    type Error = de::value::Error;
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        if self.end {
+            return Ok(None);
+        }
+
+        let key = match self.de.expect_token()? {
+            Token::Key(key) => key,
+            Token::ObjectEnd(EndMarker::Regular) => return Ok(None),
+            Token::ObjectEnd(EndMarker::Alternate) => {
+                self.end = true;
+                Cow::Borrowed(OBJECT_END_ALT_TOKEN)
+            }
+            token => return unexpected_token("key or end of object", token),
+        };
+
+        let key = seed.deserialize(KeyDeserializer::<Error>::new(key, false))?;
+
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        if self.end {
+            return seed.deserialize(().into_deserializer());
+        }
+
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct Seq<'a, 'de, R> {
+    de: &'a mut Deserializer<'de, R>,
+    // The index the next key is expected to be
+    index: u32,
+    // Whether the remaining entries have been sorted by `SequencePolicy::Lenient`
+    sorted: bool,
+    end: bool,
+}
+
+impl<'de, 'a, R> SeqAccess<'de> for Seq<'a, 'de, R>
+where
+    R: Read<'de>,
+{
+    type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
     where
         T: de::DeserializeSeed<'de>,
     {
-        if self.expect_key_or_object_end()?.is_none() {
-            return Ok(None);
+        let key = match self.de.expect_key_or_object_end()? {
+            Some(key) => key,
+            None => {
+                self.end = true;
+                return Ok(None);
+            }
+        };
+
+        let in_order = || key.parse::<u32>().ok() == Some(self.index);
+
+        match self.de.sequence_policy() {
+            SequencePolicy::Strict if !in_order() => {
+                return Err(de::Error::custom(format!(
+                    "expected sequence index {}, but got key {key:?}",
+                    self.index
+                )))
+            }
+            // Sequences are only read ahead once they turn out to be out of order
+            SequencePolicy::Lenient if !self.sorted && !in_order() => {
+                self.de.sort_sequence(key, self.index)?;
+                self.sorted = true;
+
+                return self.next_element_seed(seed);
+            }
+            _ => {}
         }
 
-        seed.deserialize(self).map(Some)
+        self.index += 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de, R> EnumAccess<'de> for &mut Deserializer<'de, R>
+where
+    R: Read<'de>,
+{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.next_key_seed(seed)? {
+            Some(variant) => Ok((variant, self)),
+            None => unexpected_token("variant", Token::ObjectEnd(EndMarker::Regular)),
+        }
+    }
+}
+
+impl<'de, R> VariantAccess<'de> for &mut Deserializer<'de, R>
+where
+    R: Read<'de>,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(de::Error::custom("expected unit variant to be a string"))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self, visitor)
     }
 }
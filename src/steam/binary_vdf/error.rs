@@ -0,0 +1,151 @@
+use std::fmt::{self, Display};
+use std::io;
+
+use serde::{de, ser};
+
+use super::token_deserializer::Token;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<u64>,
+    path: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    UnexpectedEof,
+    InvalidUtf8,
+    UnknownValueType(u8),
+    UnexpectedToken {
+        expected: &'static str,
+        actual: Token<'static>,
+    },
+    TypeMismatch {
+        expected: String,
+        actual: String,
+    },
+    LimitExceeded {
+        limit: &'static str,
+        max: usize,
+    },
+    Message(String),
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The offset of the token in the input at which the error occurred.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// The dotted path of keys leading to the value at which the error occurred,
+    /// e.g. `shortcuts.12.LaunchOptions`.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub(super) fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            offset: None,
+            path: None,
+        }
+    }
+
+    pub(super) fn unexpected_token(expected: &'static str, actual: Token<'static>) -> Self {
+        Self::new(ErrorKind::UnexpectedToken { expected, actual })
+    }
+
+    pub(super) fn limit_exceeded(limit: &'static str, max: usize) -> Self {
+        Self::new(ErrorKind::LimitExceeded { limit, max })
+    }
+
+    /// Attaches a location unless the error already has one.
+    pub(super) fn at(mut self, offset: u64, path: &[String]) -> Self {
+        if self.offset.is_none() {
+            self.offset = Some(offset);
+            self.path = Some(path.join(".")).filter(|path| !path.is_empty());
+        }
+
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if let Some(path) = &self.path {
+            write!(f, " at `{path}`")?;
+        }
+
+        if let Some(offset) = self.offset {
+            write!(f, " (offset {offset})")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Io(err) => write!(f, "{err}"),
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ErrorKind::InvalidUtf8 => write!(f, "invalid utf-8 in string"),
+            ErrorKind::UnknownValueType(byte) => write!(f, "unknown value type: 0x{byte:02x}"),
+            ErrorKind::UnexpectedToken { expected, actual } => {
+                write!(f, "expected {expected}, but got {actual:?}")
+            }
+            ErrorKind::TypeMismatch { expected, actual } => {
+                write!(f, "invalid type: {actual}, expected {expected}")
+            }
+            ErrorKind::LimitExceeded { limit, max } => write!(f, "{limit} of {max} exceeded"),
+            ErrorKind::Message(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::new(ErrorKind::UnexpectedEof),
+            _ => Error::new(ErrorKind::Io(err)),
+        }
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Message(msg.to_string()))
+    }
+
+    fn invalid_type(unexpected: de::Unexpected, expected: &dyn de::Expected) -> Self {
+        Error::new(ErrorKind::TypeMismatch {
+            expected: expected.to_string(),
+            actual: unexpected.to_string(),
+        })
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Message(msg.to_string()))
+    }
+}
@@ -0,0 +1,121 @@
+use std::ops::{Deref, DerefMut};
+
+use indexmap::IndexMap;
+use serde::de::{self, Deserialize};
+use serde::Serialize;
+
+use super::value::OBJECT_END_ALT_TOKEN;
+use super::{EndMarker, Value};
+
+/// The entries of a binary vdf object, in document order,
+/// along with the marker the object ends with.
+///
+/// Dereferences to the `IndexMap` of its entries.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Object {
+    entries: IndexMap<String, Value>,
+    end: EndMarker,
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn end_marker(&self) -> EndMarker {
+        self.end
+    }
+
+    pub fn set_end_marker(&mut self, end: EndMarker) {
+        self.end = end;
+    }
+
+    pub fn into_inner(self) -> IndexMap<String, Value> {
+        self.entries
+    }
+}
+
+impl Deref for Object {
+    type Target = IndexMap<String, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl DerefMut for Object {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entries
+    }
+}
+
+impl From<IndexMap<String, Value>> for Object {
+    fn from(entries: IndexMap<String, Value>) -> Self {
+        Self {
+            entries,
+            end: EndMarker::Regular,
+        }
+    }
+}
+
+impl<K: Into<String>> FromIterator<(K, Value)> for Object {
+    fn from_iter<I: IntoIterator<Item = (K, Value)>>(iter: I) -> Self {
+        iter.into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect::<IndexMap<_, _>>()
+            .into()
+    }
+}
+
+impl IntoIterator for Object {
+    type Item = (String, Value);
+    type IntoIter = indexmap::map::IntoIter<String, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Object {
+    type Item = (&'a String, &'a Value);
+    type IntoIter = indexmap::map::Iter<'a, String, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Object {
+    type Item = (&'a String, &'a mut Value);
+    type IntoIter = indexmap::map::IterMut<'a, String, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter_mut()
+    }
+}
+
+impl Serialize for Object {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.end {
+            EndMarker::Regular => self.entries.serialize(serializer),
+            EndMarker::Alternate => {
+                serializer.serialize_newtype_struct(OBJECT_END_ALT_TOKEN, &self.entries)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Object(object) => Ok(object),
+            _ => Err(de::Error::custom("expected a binary vdf object")),
+        }
+    }
+}
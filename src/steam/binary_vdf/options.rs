@@ -0,0 +1,126 @@
+use std::io::BufRead;
+
+use serde::de::{Deserialize, DeserializeOwned};
+
+use super::error::Result;
+use super::read::SliceRead;
+#[cfg(feature = "tokio")]
+use super::AsyncTokenDeserializer;
+use super::{from_token_deserializer, TokenDeserializer, Utf8Policy};
+
+/// Configures how binary vdf input is read.
+///
+/// The defaults are generous for any file written by Steam,
+/// but keep untrusted input from exhausting memory or the stack.
+#[derive(Clone, Debug)]
+pub struct DeserializerOptions {
+    pub(super) max_depth: usize,
+    pub(super) max_string_len: usize,
+    pub(super) max_keys: usize,
+    pub(super) utf8_policy: Utf8Policy,
+    pub(super) sequence_policy: SequencePolicy,
+    pub(super) coerce_types: bool,
+}
+
+impl DeserializerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum nesting depth of objects, including the root object.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Maximum length of a single key or string value in bytes.
+    pub fn max_string_len(mut self, max_string_len: usize) -> Self {
+        self.max_string_len = max_string_len;
+        self
+    }
+
+    /// Maximum number of keys in the whole document.
+    pub fn max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    pub fn utf8_policy(mut self, utf8_policy: Utf8Policy) -> Self {
+        self.utf8_policy = utf8_policy;
+        self
+    }
+
+    pub fn sequence_policy(mut self, sequence_policy: SequencePolicy) -> Self {
+        self.sequence_policy = sequence_policy;
+        self
+    }
+
+    /// Accept ints and strings for each other, as written by some third party tools.
+    ///
+    /// Numbers and bools may then be read from decimal strings,
+    /// with bools also accepting `"true"` and `"false"`,
+    /// and strings may be read from any integer value.
+    pub fn coerce_types(mut self, coerce_types: bool) -> Self {
+        self.coerce_types = coerce_types;
+        self
+    }
+
+    pub fn token_deserializer<R>(&self, reader: R) -> TokenDeserializer<R> {
+        TokenDeserializer::new(reader).with_options(self.clone())
+    }
+
+    pub fn from_reader<D: DeserializeOwned, R: BufRead>(&self, reader: R) -> Result<D> {
+        from_token_deserializer(self.token_deserializer(reader))
+    }
+
+    pub fn from_bytes<'de, D: Deserialize<'de>>(&self, bytes: &'de [u8]) -> Result<D> {
+        from_token_deserializer(self.token_deserializer(SliceRead::new(bytes)))
+    }
+
+    /// See `binary_vdf::from_async_reader`.
+    #[cfg(feature = "tokio")]
+    pub async fn from_async_reader<D, R>(&self, reader: R) -> Result<D>
+    where
+        D: DeserializeOwned,
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        let tokens = AsyncTokenDeserializer::new(reader)
+            .with_options(self.clone())
+            .into_buffered()
+            .await?;
+
+        from_token_deserializer(tokens)
+    }
+}
+
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_string_len: 1 << 20,
+            max_keys: usize::MAX,
+            utf8_policy: Utf8Policy::default(),
+            sequence_policy: SequencePolicy::default(),
+            coerce_types: false,
+        }
+    }
+}
+
+/// How the keys of objects are treated when deserializing them as sequences.
+///
+/// Sequences are written as objects keyed `"0"`, `"1"`, and so on.
+/// To deserialize sparse sequences with their indices intact,
+/// use a map with integer keys such as `BTreeMap<u32, T>` instead.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SequencePolicy {
+    /// Require the keys to be exactly `0..n`, in order.
+    Strict,
+    /// Order the elements by their keys, which must be unique integers.
+    /// Gaps, as left by Steam after deleting shortcuts, are skipped.
+    ///
+    /// The whole sequence has to be read ahead to sort it.
+    Lenient,
+    /// Take the elements in file order, ignoring their keys.
+    #[default]
+    FileOrder,
+}
@@ -0,0 +1,131 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+
+use super::error::{Error, ErrorKind, Result};
+use super::STRING_END;
+
+/// A source of binary vdf input.
+///
+/// Every `BufRead` is one, handing out owned strings.
+/// `SliceRead` hands out strings borrowed from the input instead.
+pub trait Read<'de> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()>;
+
+    /// Reads a NUL terminated string of at most `max_len` bytes,
+    /// consuming the terminator without returning it.
+    fn read_string(&mut self, max_len: usize) -> Result<Cow<'de, [u8]>>;
+
+    /// Like `read_string`, but only returns the length of the string.
+    fn skip_string(&mut self, max_len: usize) -> Result<usize>;
+}
+
+impl<'de, R> Read<'de> for R
+where
+    R: BufRead,
+{
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        io::Read::read_exact(self, buf)?;
+
+        Ok(())
+    }
+
+    fn read_string(&mut self, max_len: usize) -> Result<Cow<'de, [u8]>> {
+        let mut string = Vec::new();
+
+        // Allow for one more byte than the limit to find the terminator
+        let limit = (max_len as u64).saturating_add(1);
+
+        io::Read::take(self, limit).read_until(STRING_END, &mut string)?;
+
+        if string.last() != Some(&STRING_END) {
+            return Err(unterminated_string(string.len(), max_len));
+        }
+
+        string.pop();
+
+        Ok(Cow::Owned(string))
+    }
+
+    fn skip_string(&mut self, max_len: usize) -> Result<usize> {
+        let mut len = 0;
+
+        loop {
+            let buf = match self.fill_buf() {
+                Ok(buf) => buf,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            if buf.is_empty() {
+                return Err(unterminated_string(len, max_len));
+            }
+
+            let (consumed, end) = match buf.iter().position(|&byte| byte == STRING_END) {
+                Some(end) => (end + 1, Some(len + end)),
+                None => (buf.len(), None),
+            };
+
+            self.consume(consumed);
+            len += consumed;
+
+            match end {
+                Some(end) if end <= max_len => return Ok(end),
+                _ if len > max_len => return Err(Error::limit_exceeded("max_string_len", max_len)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Binary vdf input that is entirely in memory.
+pub struct SliceRead<'de> {
+    slice: &'de [u8],
+}
+
+impl<'de> SliceRead<'de> {
+    pub fn new(slice: &'de [u8]) -> Self {
+        Self { slice }
+    }
+}
+
+impl<'de> Read<'de> for SliceRead<'de> {
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        if buf.len() > self.slice.len() {
+            self.slice = &[];
+            return Err(Error::new(ErrorKind::UnexpectedEof));
+        }
+
+        let (bytes, rest) = self.slice.split_at(buf.len());
+
+        buf.copy_from_slice(bytes);
+        self.slice = rest;
+
+        Ok(())
+    }
+
+    fn read_string(&mut self, max_len: usize) -> Result<Cow<'de, [u8]>> {
+        let searched = &self.slice[..self.slice.len().min(max_len.saturating_add(1))];
+
+        let end = match searched.iter().position(|&byte| byte == STRING_END) {
+            Some(end) => end,
+            None => return Err(unterminated_string(searched.len(), max_len)),
+        };
+
+        let string = &self.slice[..end];
+        self.slice = &self.slice[end + 1..];
+
+        Ok(Cow::Borrowed(string))
+    }
+
+    fn skip_string(&mut self, max_len: usize) -> Result<usize> {
+        self.read_string(max_len).map(|string| string.len())
+    }
+}
+
+fn unterminated_string(len: usize, max_len: usize) -> Error {
+    if len > max_len {
+        return Error::limit_exceeded("max_string_len", max_len);
+    }
+
+    Error::new(ErrorKind::UnexpectedEof)
+}
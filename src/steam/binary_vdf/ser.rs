@@ -3,17 +3,22 @@ use std::io::Write;
 use serde::ser::{self, Impossible};
 use serde::{de, Serialize};
 
-use super::{ValueType, STRING_END, TokenSerializer};
+use super::error::Error;
+use super::value::{COLOR_TOKEN, OBJECT_END_ALT_TOKEN, POINTER_TOKEN, WIDE_STRING_TOKEN};
+use super::{EndMarker, TokenSerializer, ValueType};
 
-pub fn to_writer<S: Serialize, W: Write>(writer: W, value: &S) -> Result<(), de::value::Error> {
+pub fn to_writer<S: Serialize, W: Write>(writer: W, value: &S) -> Result<(), Error> {
     let mut ser = Serializer {
         token_serializer: TokenSerializer::new(writer),
+        value_type: None,
+        end: None,
+        map_ends: Vec::new(),
     };
 
     value.serialize(&mut ser)
 }
 
-pub fn to_bytes<S: Serialize>(value: &S) -> Result<Vec<u8>, de::value::Error> {
+pub fn to_bytes<S: Serialize>(value: &S) -> Result<Vec<u8>, Error> {
     let mut bytes = Vec::new();
 
     to_writer(&mut bytes, value)?;
@@ -23,6 +28,12 @@ pub fn to_bytes<S: Serialize>(value: &S) -> Result<Vec<u8>, de::value::Error> {
 
 struct Serializer<W> {
     token_serializer: TokenSerializer<W>,
+    // Set while serializing one of `Value`'s typed newtypes
+    value_type: Option<ValueType>,
+    // Set while serializing an `Object` with an alternate end, until its map starts
+    end: Option<EndMarker>,
+    // The end markers of the maps currently being serialized
+    map_ends: Vec<EndMarker>,
 }
 
 impl<'a, W> serde::Serializer for &'a mut Serializer<W>
@@ -30,15 +41,15 @@ where
     W: Write,
 {
     type Ok = ();
-    type Error = de::value::Error;
+    type Error = Error;
 
     type SerializeSeq = SerializeSeq<'a, W>;
-    type SerializeTuple = Impossible<(), de::value::Error>;
-    type SerializeTupleStruct = Impossible<(), de::value::Error>;
-    type SerializeTupleVariant = Impossible<(), de::value::Error>;
+    type SerializeTuple = SerializeSeq<'a, W>;
+    type SerializeTupleStruct = SerializeSeq<'a, W>;
+    type SerializeTupleVariant = SerializeSeq<'a, W>;
     type SerializeMap = Self;
     type SerializeStruct = Self;
-    type SerializeStructVariant = Impossible<(), de::value::Error>;
+    type SerializeStructVariant = Self;
 
     fn serialize_bool(self, value: bool) -> Result<Self::Ok, Self::Error> {
         self.serialize_u32(value as u32)
@@ -56,10 +67,8 @@ where
         self.serialize_u32(value as u32)
     }
 
-    fn serialize_i64(self, _: i64) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom(
-            "binary vdf does not support 64 bit integers",
-        ))
+    fn serialize_i64(self, value: i64) -> Result<Self::Ok, Self::Error> {
+        self.token_serializer.emit_int64(value)
     }
 
     fn serialize_i128(self, _: i128) -> Result<Self::Ok, Self::Error> {
@@ -77,13 +86,15 @@ where
     }
 
     fn serialize_u32(self, value: u32) -> Result<Self::Ok, Self::Error> {
-        self.token_serializer.emit_int(value)
+        match self.value_type.take() {
+            Some(ValueType::Pointer) => self.token_serializer.emit_pointer(value),
+            Some(ValueType::Color) => self.token_serializer.emit_color(value),
+            _ => self.token_serializer.emit_int(value),
+        }
     }
 
-    fn serialize_u64(self, _: u64) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom(
-            "binary vdf does not support 64 bit integers",
-        ))
+    fn serialize_u64(self, value: u64) -> Result<Self::Ok, Self::Error> {
+        self.token_serializer.emit_uint64(value)
     }
 
     fn serialize_u128(self, _: u128) -> Result<Self::Ok, Self::Error> {
@@ -93,7 +104,7 @@ where
     }
 
     fn serialize_f32(self, value: f32) -> Result<Self::Ok, Self::Error> {
-        self.serialize_u32(value.to_bits())
+        self.token_serializer.emit_float(value)
     }
 
     fn serialize_f64(self, _: f64) -> Result<Self::Ok, Self::Error> {
@@ -107,22 +118,25 @@ where
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
-        self.token_serializer.emit_string(value)
+        match self.value_type.take() {
+            Some(ValueType::WideString) => self.token_serializer.emit_wide_string(value),
+            _ => self.token_serializer.emit_string(value),
+        }
     }
 
-    fn serialize_bytes(self, _: &[u8]) -> Result<Self::Ok, Self::Error> {
-        Err(de::Error::custom("serializing bytes is not supported"))
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.token_serializer.emit_bytes(value)
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        Err(de::Error::custom("serializing Option is not supported"))
+        self.token_serializer.emit_none()
     }
 
-    fn serialize_some<T: ?Sized>(self, _: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
-        Err(de::Error::custom("serializing Option is not supported"))
+        value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
@@ -139,75 +153,97 @@ where
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        Err(de::Error::custom(
-            "serializing unit varints is not supported",
-        ))
+        self.token_serializer.emit_string(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
-        _value: &T,
+        name: &'static str,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
-        Err(de::Error::custom(
-            "serializing newtype structs is not supported",
-        ))
+        let value_type = match name {
+            POINTER_TOKEN => ValueType::Pointer,
+            WIDE_STRING_TOKEN => ValueType::WideString,
+            COLOR_TOKEN => ValueType::Color,
+            OBJECT_END_ALT_TOKEN => {
+                self.end = Some(EndMarker::Alternate);
+
+                return value.serialize(self);
+            }
+            _ => return value.serialize(self),
+        };
+
+        self.value_type = Some(value_type);
+
+        value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
-        Err(de::Error::custom(
-            "serializing newtype variants is not supported",
-        ))
+        self.token_serializer.emit_object_start()?;
+        self.token_serializer.emit_key(variant.to_string())?;
+
+        value.serialize(&mut *self)?;
+
+        self.token_serializer.emit_object_end(EndMarker::Regular)
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         self.token_serializer.emit_object_start()?;
 
-        Ok(SerializeSeq { i: 0, ser: self })
+        Ok(SerializeSeq {
+            i: 0,
+            ser: self,
+            variant: false,
+        })
     }
 
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        Err(de::Error::custom("serializing tuple is not supported"))
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        Err(de::Error::custom(
-            "serializing tuple struct is not supported",
-        ))
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        Err(de::Error::custom(
-            "serializing tuple variant is not supported",
-        ))
+        self.token_serializer.emit_object_start()?;
+        self.token_serializer.emit_key(variant.to_string())?;
+        self.token_serializer.emit_object_start()?;
+
+        Ok(SerializeSeq {
+            i: 0,
+            ser: self,
+            variant: true,
+        })
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
         self.token_serializer.emit_object_start()?;
+        self.map_ends.push(self.end.take().unwrap_or_default());
 
         Ok(self)
     }
@@ -226,12 +262,14 @@ where
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        Err(de::Error::custom(
-            "serializing struct variant is not supported",
-        ))
+        self.token_serializer.emit_object_start()?;
+        self.token_serializer.emit_key(variant.to_string())?;
+        self.token_serializer.emit_object_start()?;
+
+        Ok(self)
     }
 
     fn is_human_readable(&self) -> bool {
@@ -239,16 +277,16 @@ where
     }
 }
 
-impl<'a, W> ser::SerializeMap for &'a mut Serializer<W>
+impl<W> ser::SerializeMap for &mut Serializer<W>
 where
     W: Write,
 {
     type Ok = ();
-    type Error = de::value::Error;
+    type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         let key = key.serialize(KeySerializer)?;
 
@@ -257,32 +295,34 @@ where
         Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: serde::Serialize,
+        T: ?Sized + serde::Serialize,
     {
         value.serialize(&mut ** self)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        self.token_serializer.emit_object_end()
+        let end = self.map_ends.pop().unwrap_or_default();
+
+        self.token_serializer.emit_object_end(end)
     }
 }
 
-impl<'a, W> ser::SerializeStruct for &'a mut Serializer<W>
+impl<W> ser::SerializeStruct for &mut Serializer<W>
 where
     W: Write,
 {
     type Ok = ();
-    type Error = de::value::Error;
+    type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T>(
         &mut self,
         key: &'static str,
         value: &T,
     ) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         let key = key.serialize(KeySerializer)?;
         self.token_serializer.emit_key(key)?;
@@ -293,13 +333,39 @@ where
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        self.token_serializer.emit_object_end()
+        self.token_serializer.emit_object_end(EndMarker::Regular)
+    }
+}
+
+impl<W> ser::SerializeStructVariant for &mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.token_serializer.emit_object_end(EndMarker::Regular)?;
+        self.token_serializer.emit_object_end(EndMarker::Regular)
     }
 }
 
 struct SerializeSeq<'a, W> {
     ser: &'a mut Serializer<W>,
     i: usize,
+    // Whether the sequence is wrapped in an object keyed by the variant name
+    variant: bool,
 }
 
 impl<'a, W> ser::SerializeSeq for SerializeSeq<'a, W>
@@ -307,11 +373,11 @@ where
     W: Write,
 {
     type Ok = ();
-    type Error = de::value::Error;
+    type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.ser.token_serializer.emit_key(self.i.to_string())?;
 
@@ -323,7 +389,70 @@ where
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        self.ser.token_serializer.emit_object_end()
+        self.ser.token_serializer.emit_object_end(EndMarker::Regular)?;
+
+        if self.variant {
+            self.ser.token_serializer.emit_object_end(EndMarker::Regular)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, W> ser::SerializeTuple for SerializeSeq<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, W> ser::SerializeTupleStruct for SerializeSeq<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, W> ser::SerializeTupleVariant for SerializeSeq<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
     }
 }
 
@@ -331,7 +460,7 @@ struct KeySerializer;
 
 impl serde::Serializer for KeySerializer {
     type Ok = String;
-    type Error = de::value::Error;
+    type Error = Error;
 
     type SerializeSeq = Impossible<Self::Ok, Self::Error>;
     type SerializeTuple = Impossible<Self::Ok, Self::Error>;
@@ -341,40 +470,40 @@ impl serde::Serializer for KeySerializer {
     type SerializeStruct = Impossible<Self::Ok, Self::Error>;
     type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
 
-    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_bool(self, value: bool) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u32(value as u32)
     }
 
-    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_i8(self, value: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
-    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_i16(self, value: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
-    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_i32(self, value: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
-    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_i64(self, value: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
-    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_u8(self, value: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
-    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_u16(self, value: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
-    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_u32(self, value: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
-    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_u64(self, value: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
     fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
@@ -385,8 +514,16 @@ impl serde::Serializer for KeySerializer {
         Err(ser::Error::custom("unsupported key type"))
     }
 
-    fn serialize_char(self, _v: char) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+    fn serialize_i128(self, value: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_char(self, value: char) -> Result<Self::Ok, Self::Error> {
+        Ok(value.to_string())
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
@@ -401,9 +538,9 @@ impl serde::Serializer for KeySerializer {
         Err(ser::Error::custom("unsupported key type"))
     }
 
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize {
+        T: ?Sized + Serialize {
         Err(ser::Error::custom("unsupported key type"))
     }
 
@@ -419,22 +556,22 @@ impl serde::Serializer for KeySerializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("unsupported key type"))
+        Ok(variant.to_string())
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
         _name: &'static str,
-        _value: &T,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize {
-        Err(ser::Error::custom("unsupported key type"))
+        T: ?Sized + Serialize {
+        value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -442,7 +579,7 @@ impl serde::Serializer for KeySerializer {
         _value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize {
+        T: ?Sized + Serialize {
         Err(ser::Error::custom("unsupported key type"))
     }
 
@@ -0,0 +1,1220 @@
+use std::collections::BTreeMap;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    from_bytes, from_token_deserializer, to_bytes, DeserializerOptions, EndMarker, ErrorKind,
+    TokenDeserializer, Utf8Policy, Value,
+};
+use crate::steam::id::{AppId, LegacyAppId, SteamId};
+
+struct Document(Vec<u8>);
+
+impl Document {
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    fn key(mut self, value_type: u8, key: &str) -> Self {
+        self.0.push(value_type);
+        self.0.extend(key.as_bytes());
+        self.0.push(0x00);
+        self
+    }
+
+    fn object(self, key: &str) -> Self {
+        self.key(0x00, key)
+    }
+
+    fn string(self, key: &str, value: &str) -> Self {
+        let mut document = self.key(0x01, key);
+        document.0.extend(value.as_bytes());
+        document.0.push(0x00);
+        document
+    }
+
+    fn int(self, key: &str, value: u32) -> Self {
+        let mut document = self.key(0x02, key);
+        document.0.extend(value.to_le_bytes());
+        document
+    }
+
+    fn raw(self, value_type: u8, key: &str, value: &[u8]) -> Self {
+        let mut document = self.key(value_type, key);
+        document.0.extend(value);
+        document
+    }
+
+    fn end(mut self) -> Self {
+        self.0.push(0x08);
+        self
+    }
+
+    fn build(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+fn shortcuts() -> Vec<u8> {
+    Document::new()
+        .object("shortcuts")
+        .object("0")
+        .int("appid", 0x8badf00d)
+        .string("AppName", "Foo")
+        .string("Exe", "\"/usr/bin/foo\"")
+        .int("IsHidden", 0)
+        .string("FlatpakAppID", "")
+        .object("tags")
+        .string("0", "favorite")
+        .end()
+        .end()
+        .object("1")
+        .int("appid", 0xdeadbeef)
+        .string("AppName", "Bar")
+        .end()
+        .end()
+        .end()
+        .build()
+}
+
+fn assert_round_trip(bytes: &[u8]) {
+    let value = from_bytes::<Value>(bytes).unwrap();
+
+    assert_eq!(to_bytes(&value).unwrap(), bytes);
+}
+
+#[test]
+fn round_trip_shortcuts() {
+    assert_round_trip(&shortcuts());
+}
+
+#[test]
+fn round_trip_empty() {
+    assert_round_trip(&Document::new().end().build());
+    assert_round_trip(&Document::new().object("shortcuts").end().end().build());
+}
+
+#[test]
+fn round_trip_alternate_end() {
+    let mut bytes = Document::new()
+        .object("a")
+        .int("b", 1)
+        .end()
+        .object("c")
+        .end()
+        .end()
+        .build();
+    bytes[10] = 0x0B;
+    bytes[14] = 0x0B;
+
+    assert_round_trip(&bytes);
+
+    let value = from_bytes::<Value>(&bytes).unwrap();
+
+    assert_eq!(
+        value["a"].as_object().unwrap().end_marker(),
+        EndMarker::Alternate
+    );
+    assert_eq!(value.as_object().unwrap().end_marker(), EndMarker::Regular);
+}
+
+#[test]
+fn value_rejects_duplicate_keys() {
+    let bytes = Document::new()
+        .object("a")
+        .int("b", 1)
+        .int("b", 2)
+        .end()
+        .end()
+        .build();
+
+    let err = from_bytes::<Value>(&bytes).unwrap_err();
+
+    assert_eq!(err.to_string(), "duplicate key `b` at `a.b` (offset 10)");
+}
+
+#[test]
+fn round_trip_preserves_key_order() {
+    let bytes = Document::new()
+        .string("z", "1")
+        .int("a", 2)
+        .object("m")
+        .int("9", 3)
+        .int("10", 4)
+        .int("1", 5)
+        .end()
+        .end()
+        .build();
+
+    let value = from_bytes::<Value>(&bytes).unwrap();
+    let keys = value["m"].as_object().unwrap().keys().collect::<Vec<_>>();
+
+    assert_eq!(keys, ["9", "10", "1"]);
+    assert_round_trip(&bytes);
+}
+
+#[test]
+fn round_trip_preserves_int_and_string() {
+    let bytes = Document::new().int("a", 1).string("b", "1").end().build();
+
+    let value = from_bytes::<Value>(&bytes).unwrap();
+
+    assert_eq!(value["a"], Value::Int(1));
+    assert_eq!(value["b"], Value::String("1".into()));
+    assert_round_trip(&bytes);
+}
+
+#[test]
+fn round_trip_all_value_types() {
+    let bytes = Document::new()
+        .object("object")
+        .end()
+        .string("string", "foo")
+        .int("int", 42)
+        .raw(0x03, "float", &1.5f32.to_le_bytes())
+        .raw(0x04, "pointer", &7u32.to_le_bytes())
+        .raw(0x05, "wide_string", &[b'f', 0, 0xf6, 0, 0, 0])
+        .raw(0x06, "color", &[0xff, 0x80, 0x00, 0xff])
+        .raw(0x07, "uint64", &u64::MAX.to_le_bytes())
+        .raw(0x0A, "int64", &(-1i64).to_le_bytes())
+        .end()
+        .build();
+
+    let value = from_bytes::<Value>(&bytes).unwrap();
+
+    assert_eq!(value["float"], Value::Float(1.5));
+    assert_eq!(value["pointer"], Value::Pointer(7));
+    assert_eq!(value["wide_string"], Value::WideString("fö".into()));
+    assert_eq!(value["color"], Value::Color(0xff0080ff));
+    assert_eq!(value["uint64"], Value::UInt64(u64::MAX));
+    assert_eq!(value["int64"], Value::Int64(-1));
+    assert_round_trip(&bytes);
+}
+
+#[test]
+fn token_round_trip() {
+    use super::token_deserializer::Token;
+    use super::TokenSerializer;
+
+    let bytes = Document::new()
+        .string("string", "foo")
+        .int("int", 42)
+        .raw(0x03, "float", &(-0.25f32).to_le_bytes())
+        .raw(0x04, "pointer", &7u32.to_le_bytes())
+        .raw(0x05, "wide_string", &[b'f', 0, 0xf6, 0, 0, 0])
+        .raw(0x06, "color", &[0xff, 0x80, 0x00, 0xff])
+        .raw(0x07, "uint64", &u64::MAX.to_le_bytes())
+        .raw(0x0A, "int64", &i64::MIN.to_le_bytes())
+        .end()
+        .build();
+
+    let mut written = Vec::new();
+    let mut serializer = TokenSerializer::new(&mut written);
+    serializer.emit_object_start().unwrap();
+    serializer.emit_key("string".into()).unwrap();
+    serializer.emit_string("foo").unwrap();
+    serializer.emit_key("int".into()).unwrap();
+    serializer.emit_int(42).unwrap();
+    serializer.emit_key("float".into()).unwrap();
+    serializer.emit_float(-0.25).unwrap();
+    serializer.emit_key("pointer".into()).unwrap();
+    serializer.emit_pointer(7).unwrap();
+    serializer.emit_key("wide_string".into()).unwrap();
+    serializer.emit_wide_string("fö").unwrap();
+    serializer.emit_key("color".into()).unwrap();
+    serializer.emit_color(0xff0080ff).unwrap();
+    serializer.emit_key("uint64".into()).unwrap();
+    serializer.emit_uint64(u64::MAX).unwrap();
+    serializer.emit_key("int64".into()).unwrap();
+    serializer.emit_int64(i64::MIN).unwrap();
+    serializer.emit_object_end(EndMarker::Regular).unwrap();
+
+    assert_eq!(written, bytes);
+
+    let tokens = TokenDeserializer::from_slice(&bytes)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        tokens,
+        [
+            Token::ObjectStart,
+            Token::Key("string".into()),
+            Token::String("foo".into()),
+            Token::Key("int".into()),
+            Token::Int(42),
+            Token::Key("float".into()),
+            Token::Float(-0.25),
+            Token::Key("pointer".into()),
+            Token::Pointer(7),
+            Token::Key("wide_string".into()),
+            Token::WideString("fö".into()),
+            Token::Key("color".into()),
+            Token::Color(0xff0080ff),
+            Token::Key("uint64".into()),
+            Token::UInt64(u64::MAX),
+            Token::Key("int64".into()),
+            Token::Int64(i64::MIN),
+            Token::ObjectEnd(EndMarker::Regular),
+        ]
+    );
+
+    // Some files end their objects with 0x0B instead of 0x08
+    let mut bytes = Document::new().object("a").end().end().build();
+    bytes[3] = 0x0B;
+
+    let tokens = TokenDeserializer::from_slice(&bytes)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        tokens,
+        [
+            Token::ObjectStart,
+            Token::Key("a".into()),
+            Token::ObjectStart,
+            Token::ObjectEnd(EndMarker::Alternate),
+            Token::ObjectEnd(EndMarker::Regular),
+        ]
+    );
+}
+
+#[test]
+fn value_indexing() {
+    let mut value = from_bytes::<Value>(&shortcuts()).unwrap();
+
+    assert!(value.is_object());
+    assert_eq!(value["shortcuts"]["0"]["appid"].as_int(), Some(0x8badf00d));
+    assert_eq!(value["shortcuts"]["0"]["AppName"].as_str(), Some("Foo"));
+    assert_eq!(value["shortcuts"]["0"]["AppName"].as_int(), None);
+    assert_eq!(value["shortcuts"]["0"]["tags"]["0"], "favorite".into());
+    assert_eq!(value.get("missing"), None);
+    assert_eq!(value["shortcuts"]["0"]["appid"].get("appid"), None);
+
+    value["shortcuts"]["1"]["AppName"] = "Baz".into();
+    value["shortcuts"]["1"]
+        .as_object_mut()
+        .unwrap()
+        .insert("IsHidden".to_string(), 1.into());
+
+    let value = from_bytes::<Value>(&to_bytes(&value).unwrap()).unwrap();
+
+    assert_eq!(value["shortcuts"]["1"]["AppName"], "Baz".into());
+    assert_eq!(value["shortcuts"]["1"]["IsHidden"], Value::Int(1));
+}
+
+#[test]
+#[should_panic(expected = "no entry found for key \"missing\"")]
+fn value_index_missing_key() {
+    let value = from_bytes::<Value>(&shortcuts()).unwrap();
+
+    let _ = &value["shortcuts"]["missing"];
+}
+
+#[test]
+fn value_conversion() {
+    use super::{from_value, to_value};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Shortcut {
+        appid: u32,
+        #[serde(rename = "AppName")]
+        app_name: String,
+        last_played: u64,
+        offset: i64,
+        scale: f32,
+    }
+
+    let shortcut = Shortcut {
+        appid: 0xdeadbeef,
+        app_name: "Bar".to_string(),
+        last_played: u64::MAX,
+        offset: -1,
+        scale: 0.5,
+    };
+
+    let value = to_value(&shortcut).unwrap();
+
+    assert_eq!(value["appid"], Value::Int(0xdeadbeef));
+    assert_eq!(value["AppName"], Value::String("Bar".into()));
+    assert_eq!(value["last_played"], Value::UInt64(u64::MAX));
+    assert_eq!(value["offset"], Value::Int64(-1));
+    assert_eq!(value["scale"], Value::Float(0.5));
+    assert_eq!(from_value::<Shortcut>(value).unwrap(), shortcut);
+
+    // Other formats only see the inner values
+    let value = from_bytes::<Value>(
+        &Document::new()
+            .raw(0x04, "pointer", &7u32.to_le_bytes())
+            .raw(0x05, "wide_string", &[b'f', 0, 0, 0])
+            .end()
+            .build(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        r#"{"pointer":7,"wide_string":"f"}"#
+    );
+
+    // Documents are objects, so other values can't be converted
+    assert!(to_value(&1u32).is_err());
+}
+
+#[test]
+fn round_trip_unknown_fields() {
+    #[derive(Deserialize, Serialize)]
+    struct Shortcut {
+        appid: u32,
+        #[serde(rename = "AppName")]
+        app_name: String,
+        #[serde(flatten)]
+        rest: IndexMap<String, Value>,
+    }
+
+    let mut bytes = Document::new()
+        .raw(0x04, "unknown_pointer", &5u32.to_le_bytes())
+        .int("appid", 1)
+        .raw(0x05, "unknown_wide_string", &[b'f', 0, 0, 0])
+        .raw(0x06, "unknown_color", &6u32.to_le_bytes())
+        .string("AppName", "Foo")
+        .int("unknown_int", 2)
+        .string("unknown_string", "3")
+        .object("unknown_object")
+        .string("z", "")
+        .raw(0x04, "a", &4u32.to_le_bytes())
+        .end()
+        .end()
+        .build();
+    // The unknown object ends with the alternate marker
+    let len = bytes.len();
+    bytes[len - 2] = 0x0B;
+
+    let shortcut = from_bytes::<Shortcut>(&bytes).unwrap();
+
+    assert_eq!(shortcut.rest["unknown_pointer"], Value::Pointer(5));
+    assert_eq!(
+        shortcut.rest["unknown_wide_string"],
+        Value::WideString("f".into())
+    );
+    assert_eq!(shortcut.rest["unknown_color"], Value::Color(6));
+    assert_eq!(shortcut.rest["unknown_int"], Value::Int(2));
+    assert_eq!(shortcut.rest["unknown_string"], Value::String("3".into()));
+    assert_eq!(shortcut.rest["unknown_object"]["a"], Value::Pointer(4));
+
+    // The derived `Serialize` writes the unknown fields after the known ones
+    let written = from_bytes::<Value>(&to_bytes(&shortcut).unwrap()).unwrap();
+
+    assert_eq!(written, from_bytes::<Value>(&bytes).unwrap());
+    assert_eq!(
+        written["unknown_object"].as_object().unwrap().end_marker(),
+        EndMarker::Alternate
+    );
+}
+
+#[test]
+fn error_reports_path_and_offset() {
+    #[allow(dead_code)]
+    #[derive(Debug, Deserialize)]
+    struct Shortcut {
+        #[serde(rename = "IsHidden")]
+        is_hidden: bool,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Debug, Deserialize)]
+    struct ShortcutsVdf {
+        shortcuts: Vec<Shortcut>,
+    }
+
+    let bytes = Document::new()
+        .object("shortcuts")
+        .object("0")
+        .string("IsHidden", "yes")
+        .end()
+        .end()
+        .end()
+        .build();
+
+    let err = from_bytes::<ShortcutsVdf>(&bytes).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedToken { .. }));
+    assert_eq!(err.path(), Some("shortcuts.0.IsHidden"));
+    assert_eq!(err.offset(), Some(24));
+}
+
+#[test]
+fn error_reports_unexpected_eof() {
+    let bytes = Document::new().string("a", "b").build();
+
+    let err = from_bytes::<Value>(&bytes[..bytes.len() - 1]).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.path(), Some("a"));
+}
+
+#[test]
+fn option_fields() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Shortcut {
+        #[serde(rename = "AppName")]
+        app_name: String,
+        #[serde(rename = "FlatpakAppID")]
+        flatpak_app_id: Option<String>,
+        #[serde(rename = "sortas")]
+        sort_as: Option<String>,
+    }
+
+    let bytes = Document::new()
+        .string("AppName", "Foo")
+        .string("sortas", "foo")
+        .end()
+        .build();
+
+    let shortcut = from_bytes::<Shortcut>(&bytes).unwrap();
+
+    assert_eq!(
+        shortcut,
+        Shortcut {
+            app_name: "Foo".into(),
+            flatpak_app_id: None,
+            sort_as: Some("foo".into()),
+        }
+    );
+    assert_eq!(to_bytes(&shortcut).unwrap(), bytes);
+}
+
+#[test]
+fn enums() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Kind {
+        Unit,
+        Newtype(u32),
+        Tuple(u32, String),
+        Struct { a: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Kinds {
+        unit: Kind,
+        newtype: Kind,
+        tuple: Kind,
+        r#struct: Kind,
+    }
+
+    let kinds = Kinds {
+        unit: Kind::Unit,
+        newtype: Kind::Newtype(1),
+        tuple: Kind::Tuple(2, "3".into()),
+        r#struct: Kind::Struct { a: 4 },
+    };
+
+    let bytes = Document::new()
+        .string("unit", "Unit")
+        .object("newtype")
+        .int("Newtype", 1)
+        .end()
+        .object("tuple")
+        .object("Tuple")
+        .int("0", 2)
+        .string("1", "3")
+        .end()
+        .end()
+        .object("struct")
+        .object("Struct")
+        .int("a", 4)
+        .end()
+        .end()
+        .end()
+        .build();
+
+    assert_eq!(to_bytes(&kinds).unwrap(), bytes);
+    assert_eq!(from_bytes::<Kinds>(&bytes).unwrap(), kinds);
+}
+
+#[test]
+fn unit_variant_from_index() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum OpenVr {
+        Off,
+        On,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Shortcut {
+        #[serde(rename = "OpenVR")]
+        open_vr: OpenVr,
+    }
+
+    let bytes = Document::new().int("OpenVR", 1).end().build();
+
+    assert_eq!(from_bytes::<Shortcut>(&bytes).unwrap().open_vr, OpenVr::On);
+}
+
+#[test]
+fn unit_variant_discriminants() {
+    use enum_primitive_derive::Primitive;
+
+    use crate::serde_utils::primitive;
+
+    #[derive(Primitive, Debug, PartialEq)]
+    enum CompatMode {
+        Default = 0,
+        Forced = 250,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Shortcut {
+        #[serde(with = "primitive")]
+        mode: CompatMode,
+    }
+
+    let bytes = Document::new().int("mode", 250).end().build();
+    let shortcut = from_bytes::<Shortcut>(&bytes).unwrap();
+
+    assert_eq!(shortcut.mode, CompatMode::Forced);
+    assert_eq!(to_bytes(&shortcut).unwrap(), bytes);
+
+    let bytes = Document::new().int("mode", 1).end().build();
+    let err = from_bytes::<Shortcut>(&bytes).unwrap_err();
+
+    assert_eq!(err.path(), Some("mode"));
+}
+
+#[test]
+fn newtypes_and_tuples() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Point(u32, u32);
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Ids {
+        legacy_app_id: LegacyAppId,
+        steam_id: SteamId,
+        point: Point,
+        pair: (String, u32),
+    }
+
+    let ids = Ids {
+        legacy_app_id: LegacyAppId::from(AppId::from(0x8badf00d)),
+        steam_id: SteamId::from(76561197960287930),
+        point: Point(1, 2),
+        pair: ("a".into(), 3),
+    };
+
+    let bytes = Document::new()
+        .raw(0x07, "legacy_app_id", &0x8badf00d_02000000u64.to_le_bytes())
+        .raw(0x07, "steam_id", &76561197960287930u64.to_le_bytes())
+        .object("point")
+        .int("0", 1)
+        .int("1", 2)
+        .end()
+        .object("pair")
+        .string("0", "a")
+        .int("1", 3)
+        .end()
+        .end()
+        .build();
+
+    assert_eq!(to_bytes(&ids).unwrap(), bytes);
+    assert_eq!(from_bytes::<Ids>(&bytes).unwrap(), ids);
+}
+
+#[test]
+fn invalid_utf8() {
+    let bytes = Document::new()
+        .raw(0x01, "AppName", b"Caf\xe9\0")
+        .end()
+        .build();
+
+    let err = from_bytes::<Value>(&bytes).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::InvalidUtf8));
+
+    let tokens = TokenDeserializer::new(&bytes[..]).with_utf8_policy(Utf8Policy::Lossy);
+    let value = from_token_deserializer::<Value, _>(tokens).unwrap();
+    assert_eq!(value["AppName"].as_str(), Some("Caf\u{fffd}"));
+
+    let tokens = TokenDeserializer::new(&bytes[..]).with_utf8_policy(Utf8Policy::Bytes);
+    let value = from_token_deserializer::<Value, _>(tokens).unwrap();
+    assert_eq!(value["AppName"], Value::Bytes(b"Caf\xe9".to_vec()));
+    assert_eq!(to_bytes(&value).unwrap(), bytes);
+}
+
+#[test]
+fn limits() {
+    let nested = Document::new()
+        .object("a")
+        .object("b")
+        .end()
+        .end()
+        .end()
+        .build();
+
+    let err = DeserializerOptions::new()
+        .max_depth(2)
+        .from_bytes::<Value>(&nested)
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LimitExceeded { .. }));
+    assert_eq!(err.path(), Some("a.b"));
+
+    let string = Document::new().string("a", "0123456789").end().build();
+
+    let options = DeserializerOptions::new().max_string_len(10);
+    assert!(options.from_bytes::<Value>(&string).is_ok());
+
+    let options = DeserializerOptions::new().max_string_len(9);
+    let err = options.from_bytes::<Value>(&string).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LimitExceeded { .. }));
+
+    let err = DeserializerOptions::new()
+        .max_keys(1)
+        .from_bytes::<Value>(&nested)
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::LimitExceeded { .. }));
+}
+
+#[test]
+fn borrows_from_slice() {
+    use std::borrow::Cow;
+
+    #[derive(Deserialize)]
+    struct Shortcut<'a> {
+        #[serde(rename = "AppName")]
+        app_name: &'a str,
+        #[serde(rename = "Exe", borrow)]
+        exe: Cow<'a, str>,
+        tags: IndexMap<&'a str, &'a str>,
+    }
+
+    let bytes = Document::new()
+        .string("AppName", "Half-Life")
+        .string("Exe", "hl.exe")
+        .object("tags")
+        .string("0", "FPS")
+        .end()
+        .end()
+        .build();
+
+    let shortcut = from_bytes::<Shortcut>(&bytes).unwrap();
+    assert_eq!(shortcut.app_name, "Half-Life");
+    assert!(matches!(shortcut.exe, Cow::Borrowed("hl.exe")));
+    assert_eq!(shortcut.tags["0"], "FPS");
+
+    // Readers can't lend out their buffer, so `&str` fails there
+    let tokens = TokenDeserializer::new(&bytes[..]);
+    let err = from_token_deserializer::<Shortcut, _>(tokens)
+        .err()
+        .unwrap();
+    assert!(matches!(err.kind(), ErrorKind::TypeMismatch { .. }));
+}
+
+#[test]
+fn pull_parser() {
+    use super::token_deserializer::Token;
+
+    let bytes = shortcuts();
+    let mut tokens = TokenDeserializer::new(&bytes[..]);
+
+    assert_eq!(tokens.next().unwrap().unwrap(), Token::ObjectStart);
+    assert_eq!(
+        tokens.next().unwrap().unwrap(),
+        Token::Key("shortcuts".into())
+    );
+    assert_eq!(tokens.path(), ["shortcuts"]);
+    assert_eq!(tokens.depth(), 1);
+
+    tokens.skip_value().unwrap();
+    assert_eq!(tokens.path(), ["shortcuts"]);
+    assert_eq!(tokens.depth(), 1);
+
+    assert_eq!(
+        tokens.next().unwrap().unwrap(),
+        Token::ObjectEnd(EndMarker::Regular)
+    );
+    assert_eq!(tokens.position(), bytes.len() as u64);
+    assert!(tokens.next().is_none());
+
+    // Skipping the whole document reads exactly as much as parsing it
+    let mut tokens = TokenDeserializer::from_slice(&bytes);
+    tokens.skip_value().unwrap();
+    assert_eq!(tokens.position(), bytes.len() as u64);
+    assert!(tokens.next().is_none());
+
+    let err = TokenDeserializer::from_slice(&bytes[..bytes.len() - 1])
+        .skip_value()
+        .unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn async_round_trip() {
+    use super::token_deserializer::Token;
+    use super::{from_async_reader, to_async_writer, AsyncTokenDeserializer};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let bytes = shortcuts();
+        let mut input = bytes.clone();
+        input.extend(b"trailing");
+
+        let mut reader = tokio::io::BufReader::with_capacity(3, &input[..]);
+        let value = from_async_reader::<Value, _>(&mut reader).await.unwrap();
+        assert_eq!(value, from_bytes::<Value>(&bytes).unwrap());
+
+        // Like `from_reader`, this stops right after the document
+        let mut rest = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut rest)
+            .await
+            .unwrap();
+        assert_eq!(rest, b"trailing");
+
+        let mut output = Vec::new();
+        to_async_writer(&mut output, &value).await.unwrap();
+        assert_eq!(output, bytes);
+
+        let mut tokens = AsyncTokenDeserializer::new(&bytes[..]);
+        let mut sync_tokens = TokenDeserializer::new(&bytes[..]);
+
+        while let Some(token) = tokens.next().await {
+            assert_eq!(token.unwrap(), sync_tokens.next().unwrap().unwrap());
+            assert_eq!(tokens.path(), sync_tokens.path());
+            assert_eq!(tokens.position(), sync_tokens.position());
+        }
+        assert!(sync_tokens.next().is_none());
+
+        let mut tokens = AsyncTokenDeserializer::new(&bytes[..]);
+        assert_eq!(tokens.next().await.unwrap().unwrap(), Token::ObjectStart);
+        assert_eq!(
+            tokens.next().await.unwrap().unwrap(),
+            Token::Key("shortcuts".into())
+        );
+        tokens.skip_value().await.unwrap();
+        assert_eq!(
+            tokens.next().await.unwrap().unwrap(),
+            Token::ObjectEnd(EndMarker::Regular)
+        );
+        assert!(tokens.next().await.is_none());
+
+        // Skipped values are read without decoding them, and no further
+        let mut skipped = Document::new()
+            .object("a")
+            .string("b", "c")
+            .end()
+            .int("d", 1)
+            .end()
+            .build();
+        let b = skipped.iter().position(|&byte| byte == b'c').unwrap();
+        skipped[b] = 0xFF;
+        skipped.extend(b"trailing");
+        let mut reader = tokio::io::BufReader::with_capacity(3, &skipped[..]);
+        let mut tokens = AsyncTokenDeserializer::new(&mut reader);
+        assert_eq!(tokens.next().await.unwrap().unwrap(), Token::ObjectStart);
+        assert_eq!(
+            tokens.next().await.unwrap().unwrap(),
+            Token::Key("a".into())
+        );
+        tokens.skip_value().await.unwrap();
+        assert_eq!(tokens.path(), ["a"]);
+        assert_eq!(
+            tokens.next().await.unwrap().unwrap(),
+            Token::Key("d".into())
+        );
+        tokens.skip_value().await.unwrap();
+        assert_eq!(
+            tokens.next().await.unwrap().unwrap(),
+            Token::ObjectEnd(EndMarker::Regular)
+        );
+        assert!(tokens.next().await.is_none());
+        let mut rest = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut rest)
+            .await
+            .unwrap();
+        assert_eq!(rest, b"trailing");
+
+        let err = AsyncTokenDeserializer::new(&skipped[..7])
+            .skip_value()
+            .await
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+
+        // The token deserializer stops right after the document
+        let mut reader = &input[..];
+        let mut tokens = AsyncTokenDeserializer::new(&mut reader);
+        while let Some(token) = tokens.next().await {
+            token.unwrap();
+        }
+        assert_eq!(reader, b"trailing");
+
+        let err = from_async_reader::<Value, _>(&bytes[..bytes.len() - 1])
+            .await
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+        assert_eq!(err.offset(), Some(bytes.len() as u64 - 1));
+    });
+}
+
+#[test]
+fn sequence_indices() {
+    use super::SequencePolicy;
+
+    let tags = |keys: &[&str]| {
+        let mut document = Document::new().object("tags");
+
+        for key in keys {
+            document = document.string(key, &format!("tag{key}"));
+        }
+
+        document.end().end().build()
+    };
+    let read = |policy, bytes: &[u8]| {
+        DeserializerOptions::new()
+            .sequence_policy(policy)
+            .from_bytes::<IndexMap<String, Vec<String>>>(bytes)
+            .map(|mut value| value.remove("tags").unwrap())
+    };
+
+    let in_order = tags(&["0", "1", "2"]);
+    let out_of_order = tags(&["0", "5", "2"]);
+    let duplicate = tags(&["0", "1", "0"]);
+    let not_an_index = tags(&["0", "x"]);
+
+    for policy in [
+        SequencePolicy::Strict,
+        SequencePolicy::Lenient,
+        SequencePolicy::FileOrder,
+    ] {
+        assert_eq!(read(policy, &in_order).unwrap(), ["tag0", "tag1", "tag2"]);
+    }
+
+    let err = read(SequencePolicy::Strict, &out_of_order).unwrap_err();
+    assert_eq!(err.path(), Some("tags.5"));
+    assert!(read(SequencePolicy::Strict, &duplicate).is_err());
+
+    assert_eq!(
+        read(SequencePolicy::Lenient, &out_of_order).unwrap(),
+        ["tag0", "tag2", "tag5"]
+    );
+    assert!(read(SequencePolicy::Lenient, &duplicate).is_err());
+    assert!(read(SequencePolicy::Lenient, &not_an_index).is_err());
+    assert!(read(SequencePolicy::Lenient, &tags(&["1", "0", "1"])).is_err());
+
+    assert_eq!(
+        read(SequencePolicy::FileOrder, &out_of_order).unwrap(),
+        ["tag0", "tag5", "tag2"]
+    );
+
+    // Sparse sequences keep their indices in a map
+    let sparse = from_bytes::<IndexMap<String, BTreeMap<u32, String>>>(&out_of_order).unwrap();
+    assert_eq!(
+        sparse["tags"],
+        BTreeMap::from([
+            (0, "tag0".to_string()),
+            (2, "tag2".to_string()),
+            (5, "tag5".to_string()),
+        ])
+    );
+}
+
+#[test]
+fn nested_out_of_order_sequences() {
+    use super::SequencePolicy;
+
+    let bytes = Document::new()
+        .object("1")
+        .string("1", "d")
+        .string("0", "c")
+        .end()
+        .object("0")
+        .string("1", "b")
+        .string("0", "a")
+        .end()
+        .end()
+        .build();
+
+    let value = DeserializerOptions::new()
+        .sequence_policy(SequencePolicy::Lenient)
+        .from_bytes::<Vec<Vec<String>>>(&bytes)
+        .unwrap();
+    assert_eq!(value, [["a", "b"], ["c", "d"]]);
+
+    // Keys are ignored unless asked otherwise
+    let value = from_bytes::<Vec<Vec<String>>>(&bytes).unwrap();
+    assert_eq!(value, [["d", "c"], ["b", "a"]]);
+
+    // Errors point at the element as it appears in the input
+    let bytes = Document::new()
+        .object("tags")
+        .int("1", 1)
+        .string("0", "x")
+        .end()
+        .end()
+        .build();
+
+    let err = DeserializerOptions::new()
+        .sequence_policy(SequencePolicy::Lenient)
+        .from_bytes::<IndexMap<String, Vec<u32>>>(&bytes)
+        .unwrap_err();
+    assert_eq!(err.path(), Some("tags.0"));
+    assert_eq!(err.offset(), Some(16));
+
+    let bytes = Document::new()
+        .object("tags")
+        .int("1", 1)
+        .int("0", 0)
+        .int("1", 1)
+        .end()
+        .end()
+        .build();
+
+    let err = DeserializerOptions::new()
+        .sequence_policy(SequencePolicy::Lenient)
+        .from_bytes::<IndexMap<String, Vec<u32>>>(&bytes)
+        .unwrap_err();
+    assert_eq!(err.path(), Some("tags.1"));
+    assert_eq!(err.offset(), Some(20));
+}
+
+#[test]
+fn type_coercion() {
+    use crate::serde_utils::deserialize_coerced;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Shortcut {
+        appid: AppId,
+        #[serde(rename = "IsHidden")]
+        is_hidden: bool,
+        #[serde(rename = "AppName")]
+        app_name: String,
+        #[serde(rename = "LastPlayTime")]
+        last_play_time: Option<u32>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct FlattenedShortcut {
+        #[serde(deserialize_with = "deserialize_coerced")]
+        appid: AppId,
+        #[serde(rename = "IsHidden", deserialize_with = "deserialize_coerced")]
+        is_hidden: bool,
+        #[serde(rename = "AppName", deserialize_with = "deserialize_coerced")]
+        app_name: String,
+        #[serde(
+            rename = "LastPlayTime",
+            default,
+            deserialize_with = "deserialize_coerced"
+        )]
+        last_play_time: Option<u32>,
+        #[serde(flatten)]
+        rest: IndexMap<String, Value>,
+    }
+
+    let bytes = Document::new()
+        .string("appid", "3735928559")
+        .string("IsHidden", "true")
+        .int("AppName", 1234)
+        .end()
+        .build();
+
+    assert!(from_bytes::<Shortcut>(&bytes).is_err());
+
+    let shortcut = DeserializerOptions::new()
+        .coerce_types(true)
+        .from_bytes::<Shortcut>(&bytes)
+        .unwrap();
+    assert_eq!(
+        shortcut,
+        Shortcut {
+            appid: AppId::from(0xdeadbeef),
+            is_hidden: true,
+            app_name: "1234".to_string(),
+            last_play_time: None,
+        }
+    );
+
+    let shortcut = from_bytes::<FlattenedShortcut>(&bytes).unwrap();
+    assert_eq!(shortcut.appid, AppId::from(0xdeadbeef));
+    assert!(shortcut.is_hidden);
+    assert_eq!(shortcut.app_name, "1234");
+    assert_eq!(shortcut.last_play_time, None);
+
+    let bytes = Document::new()
+        .string("appid", "1")
+        .string("IsHidden", "yes")
+        .string("AppName", "")
+        .end()
+        .build();
+
+    let options = DeserializerOptions::new().coerce_types(true);
+    assert!(options.from_bytes::<Shortcut>(&bytes).is_err());
+    assert!(from_bytes::<FlattenedShortcut>(&bytes).is_err());
+}
+
+#[test]
+fn type_coercion_newtypes() {
+    use crate::serde_utils::deserialize_coerced;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Id(u32);
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Shortcut {
+        #[serde(deserialize_with = "deserialize_coerced")]
+        id: Id,
+        #[serde(deserialize_with = "deserialize_coerced")]
+        parent: Option<Id>,
+        #[serde(flatten)]
+        rest: IndexMap<String, Value>,
+    }
+
+    let bytes = Document::new()
+        .string("id", "5")
+        .string("parent", "6")
+        .end()
+        .build();
+
+    let shortcut = from_bytes::<Shortcut>(&bytes).unwrap();
+    assert_eq!(shortcut.id, Id(5));
+    assert_eq!(shortcut.parent, Some(Id(6)));
+}
+
+#[test]
+fn case_insensitive_fields() {
+    use crate::serde_utils::CaseInsensitive;
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    #[serde(rename_all = "PascalCase")]
+    struct Shortcut {
+        #[serde(rename = "appid")]
+        app_id: u32,
+        app_name: String,
+        exe: String,
+        start_dir: String,
+        last_play_time: Option<u32>,
+    }
+
+    let bytes = Document::new()
+        .int("AppID", 1)
+        .string("appname", "Foo")
+        .string("exe", "foo.exe")
+        .string("StartDir", "/")
+        .int("unknown", 2)
+        .end()
+        .build();
+
+    let shortcut = from_bytes::<CaseInsensitive<Shortcut, Value>>(&bytes).unwrap();
+
+    assert_eq!(
+        *shortcut,
+        Shortcut {
+            app_id: 1,
+            app_name: "Foo".into(),
+            exe: "foo.exe".into(),
+            start_dir: "/".into(),
+            last_play_time: None,
+        }
+    );
+    assert_eq!(shortcut.rest["unknown"], Value::Int(2));
+    assert_eq!(shortcut.spelling("AppName"), "appname");
+    assert_eq!(shortcut.spelling("StartDir"), "StartDir");
+    assert_eq!(to_bytes(&shortcut).unwrap(), bytes);
+
+    // Unknown keys are written back where they were
+    let bytes = Document::new()
+        .int("first", 0)
+        .int("appid", 1)
+        .string("AppName", "Foo")
+        .int("icon", 2)
+        .string("tags", "")
+        .string("Exe", "foo.exe")
+        .string("StartDir", "/")
+        .int("last", 3)
+        .end()
+        .build();
+
+    let shortcut = from_bytes::<CaseInsensitive<Shortcut, Value>>(&bytes).unwrap();
+    assert_eq!(to_bytes(&shortcut).unwrap(), bytes);
+
+    // Two spellings of the same field are rejected rather than one of them dropped
+    let bytes = Document::new()
+        .int("appid", 1)
+        .string("AppName", "Foo")
+        .string("APPNAME", "Bar")
+        .string("Exe", "")
+        .string("StartDir", "")
+        .end()
+        .build();
+
+    let error = from_bytes::<CaseInsensitive<Shortcut, Value>>(&bytes).unwrap_err();
+    assert!(error.to_string().contains("duplicate field `AppName`"));
+}
+
+#[test]
+fn non_string_map_keys() {
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+    enum Platform {
+        Windows,
+        Linux,
+    }
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+    struct Index(u8);
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Keys {
+        apps: BTreeMap<AppId, String>,
+        signed: BTreeMap<i64, u32>,
+        chars: BTreeMap<char, u32>,
+        flags: BTreeMap<bool, u32>,
+        indices: BTreeMap<Index, u32>,
+        platforms: BTreeMap<Platform, u32>,
+    }
+
+    let keys = Keys {
+        apps: BTreeMap::from([(AppId::from(440), "tf2".into())]),
+        signed: BTreeMap::from([(-1, 1)]),
+        chars: BTreeMap::from([('x', 2)]),
+        flags: BTreeMap::from([(false, 3), (true, 4)]),
+        indices: BTreeMap::from([(Index(7), 5)]),
+        platforms: BTreeMap::from([(Platform::Windows, 6), (Platform::Linux, 7)]),
+    };
+
+    let bytes = Document::new()
+        .object("apps")
+        .string("440", "tf2")
+        .end()
+        .object("signed")
+        .int("-1", 1)
+        .end()
+        .object("chars")
+        .int("x", 2)
+        .end()
+        .object("flags")
+        .int("0", 3)
+        .int("1", 4)
+        .end()
+        .object("indices")
+        .int("7", 5)
+        .end()
+        .object("platforms")
+        .int("Windows", 6)
+        .int("Linux", 7)
+        .end()
+        .end()
+        .build();
+
+    assert_eq!(to_bytes(&keys).unwrap(), bytes);
+    assert_eq!(from_bytes::<Keys>(&bytes).unwrap(), keys);
+
+    let bytes = Document::new()
+        .object("apps")
+        .string("tf2", "440")
+        .end()
+        .end()
+        .build();
+
+    let error = from_bytes::<IndexMap<String, BTreeMap<AppId, String>>>(&bytes).unwrap_err();
+    assert!(error.to_string().contains("invalid value: string \"tf2\""));
+}
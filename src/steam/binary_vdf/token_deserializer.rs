@@ -1,36 +1,171 @@
+use std::borrow::Cow;
 use std::io::BufRead;
+use std::str;
+use std::sync::Arc;
 
-use anyhow::{Context, Result};
 use num_traits::FromPrimitive;
 
-use super::{ValueType, OBJECT_END, STRING_END};
-
+use super::error::{Error, ErrorKind, Result};
+use super::read::{Read, SliceRead};
+use super::DeserializerOptions;
+use super::{EndMarker, ValueType};
+
+/// A pull parser for binary vdf, yielding the document as a flat stream of `Token`s.
+///
+/// Values that are not of interest can be passed over with `skip_value`,
+/// which neither decodes nor allocates them:
+///
+/// ```
+/// use hbc::steam::binary_vdf::token_deserializer::{Token, TokenDeserializer};
+///
+/// fn app_name(data: &[u8]) -> hbc::steam::binary_vdf::Result<Option<String>> {
+///     let target = ["common", "name"].map(String::from);
+///     let mut tokens = TokenDeserializer::from_slice(data);
+///
+///     while let Some(token) = tokens.next() {
+///         match token? {
+///             Token::Key(_) if !target.starts_with(tokens.path()) => tokens.skip_value()?,
+///             Token::String(name) => return Ok(Some(name.into_owned())),
+///             _ => {}
+///         }
+///     }
+///
+///     Ok(None)
+/// }
+/// ```
 pub struct TokenDeserializer<R> {
     reader: R,
     next_op: NextOp,
     depth: usize,
+    key_table: Option<Arc<[String]>>,
+    options: DeserializerOptions,
+    keys: usize,
+    position: u64,
+    token_offset: u64,
+    // Keys are copied into reused buffers, so that tracking the path
+    // doesn't cost an allocation per key
+    path: Vec<String>,
+    path_len: usize,
 }
 
-impl<R> TokenDeserializer<R>
-where
-    R: BufRead,
-{
+impl<'de> TokenDeserializer<SliceRead<'de>> {
+    /// Reads from memory, borrowing keys and string values from `bytes`
+    /// whenever possible instead of copying them.
+    pub fn from_slice(bytes: &'de [u8]) -> Self {
+        Self::new(SliceRead::new(bytes))
+    }
+}
+
+impl<R> TokenDeserializer<R> {
     pub fn new(reader: R) -> Self {
         Self {
             reader,
             next_op: NextOp::ParseValue(ValueType::Object),
             depth: 0,
+            key_table: None,
+            options: DeserializerOptions::default(),
+            keys: 0,
+            position: 0,
+            token_offset: 0,
+            path: Vec::new(),
+            path_len: 0,
         }
     }
+
+    /// Reads keys as little endian indices into `key_table`
+    /// instead of as inline strings, like appinfo.vdf v29 does.
+    pub fn with_key_table(mut self, key_table: Arc<[String]>) -> Self {
+        self.key_table = Some(key_table);
+        self
+    }
+
+    pub fn with_options(mut self, options: DeserializerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_utf8_policy(mut self, utf8_policy: Utf8Policy) -> Self {
+        self.options.utf8_policy = utf8_policy;
+        self
+    }
+
+    /// The number of objects enclosing the next token, including the root object.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The keys leading to the most recent token.
+    ///
+    /// After a `Token::Key` this ends with that key, and it keeps doing
+    /// so for the following value, up to and including its `Token::ObjectEnd`.
+    pub fn path(&self) -> &[String] {
+        &self.path[..self.path_len]
+    }
+
+    /// The number of bytes read so far, which is the offset of the next token.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// The offset of the most recent token.
+    pub(super) fn token_offset(&self) -> u64 {
+        self.token_offset
+    }
+
+    /// Attaches the location of the most recent token to `err`.
+    pub(super) fn locate(&self, err: Error) -> Error {
+        err.at(self.token_offset, self.path())
+    }
+
+    /// Stops at `err`, which occurred while reading ahead of the next token.
+    #[cfg(feature = "tokio")]
+    pub(super) fn fail(&mut self, err: Error) -> Error {
+        self.next_op = NextOp::DoNothing;
+        self.token_offset = self.position;
+        self.locate(err)
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(super) fn next_op(&self) -> NextOp {
+        self.next_op
+    }
+
+    pub(super) fn options(&self) -> &DeserializerOptions {
+        &self.options
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(super) fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
 }
 
 impl<R> Iterator for TokenDeserializer<R>
 where
     R: BufRead,
 {
-    type Item = Result<Token>;
+    type Item = Result<Token<'static>>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        self.next_token()
+    }
+}
+
+impl<'de> Iterator for TokenDeserializer<SliceRead<'de>> {
+    type Item = Result<Token<'de>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token()
+    }
+}
+
+impl<'de, R> TokenDeserializer<R>
+where
+    R: Read<'de>,
+{
+    pub(super) fn next_token(&mut self) -> Option<Result<Token<'de>>> {
+        self.token_offset = self.position;
+
         let result = match self.next_op {
             NextOp::ParseValue(value_type) => self.handle_parse_value(value_type),
             NextOp::ParseKey => self.handle_parse_key(),
@@ -44,21 +179,104 @@ where
             }
             Err(err) => {
                 self.next_op = NextOp::DoNothing;
-                Some(Err(err))
+                Some(Err(self.locate(err)))
             }
         }
     }
-}
 
-impl<R> TokenDeserializer<R>
-where
-    R: BufRead,
-{
-    fn handle_parse_key(&mut self) -> Result<(Token, NextOp)> {
+    /// Skips over the value of the most recent `Token::Key`,
+    /// including everything within it if it is an object.
+    ///
+    /// Skipped strings are not checked for valid UTF-8, but all limits still apply.
+    /// Called before the first token, this skips the whole document.
+    pub fn skip_value(&mut self) -> Result<()> {
+        self.token_offset = self.position;
+
+        let value_type = match self.next_op {
+            NextOp::ParseValue(value_type) => value_type,
+            NextOp::ParseKey | NextOp::DoNothing => {
+                return Err(self.locate(Error::new(ErrorKind::Message(
+                    "skip_value must follow a key".to_string(),
+                ))))
+            }
+        };
+
+        match self.handle_skip_value(value_type) {
+            Ok(()) => {
+                self.next_op = if self.depth == 0 {
+                    NextOp::DoNothing
+                } else {
+                    NextOp::ParseKey
+                };
+
+                Ok(())
+            }
+            Err(err) => {
+                self.next_op = NextOp::DoNothing;
+                Err(self.locate(err))
+            }
+        }
+    }
+
+    fn handle_skip_value(&mut self, mut value_type: ValueType) -> Result<()> {
+        // Objects opened while skipping, tracked without touching `depth` or `path`
+        let mut skipped_depth = 0;
+
+        loop {
+            match value_type {
+                ValueType::Object => {
+                    if self.depth + skipped_depth >= self.options.max_depth {
+                        return Err(Error::limit_exceeded("max_depth", self.options.max_depth));
+                    }
+
+                    skipped_depth += 1;
+                }
+                ValueType::String => self.skip_string()?,
+                ValueType::WideString => {
+                    self.read_wide_string_units()?;
+                }
+                ValueType::Int | ValueType::Float | ValueType::Pointer | ValueType::Color => {
+                    self.read_int()?;
+                }
+                ValueType::UInt64 | ValueType::Int64 => {
+                    self.read_int64()?;
+                }
+            }
+
+            value_type = loop {
+                if skipped_depth == 0 {
+                    return Ok(());
+                }
+
+                match self.read_value_type()? {
+                    Ok(value_type) => break value_type,
+                    Err(_) => skipped_depth -= 1,
+                }
+            };
+
+            self.keys += 1;
+
+            if self.keys > self.options.max_keys {
+                return Err(Error::limit_exceeded("max_keys", self.options.max_keys));
+            }
+
+            match self.key_table {
+                Some(_) => {
+                    self.read_int()?;
+                }
+                None => self.skip_string()?,
+            }
+        }
+    }
+
+    fn handle_parse_key(&mut self) -> Result<(Token<'de>, NextOp)> {
+        // Drop the key of the previous entry, if any
+        self.path_len = self.path_len.min(self.depth - 1);
+
         let value_type = self.read_value_type()?;
         let value_type = match value_type {
-            Some(value_type) => value_type,
-            None => {
+            Ok(value_type) => value_type,
+            Err(end) => {
                 self.depth -= 1;
 
                 let next_op = if self.depth == 0 {
@@ -67,57 +285,191 @@ where
                     NextOp::ParseKey
                 };
 
-                return Ok((Token::ObjectEnd, next_op));
+                return Ok((Token::ObjectEnd(end), next_op));
             }
         };
 
-        let key = self.read_string()?;
+        self.keys += 1;
+
+        if self.keys > self.options.max_keys {
+            return Err(Error::limit_exceeded("max_keys", self.options.max_keys));
+        }
+
+        let key = self.read_key()?;
+
+        self.push_path(&key);
 
         Ok((Token::Key(key), NextOp::ParseValue(value_type)))
     }
 
-    fn handle_parse_value(&mut self, value_type: ValueType) -> Result<(Token, NextOp)> {
+    fn push_path(&mut self, key: &str) {
+        match self.path.get_mut(self.path_len) {
+            Some(buf) => {
+                buf.clear();
+                buf.push_str(key);
+            }
+            None => self.path.push(key.to_string()),
+        }
+
+        self.path_len += 1;
+    }
+
+    fn handle_parse_value(&mut self, value_type: ValueType) -> Result<(Token<'de>, NextOp)> {
         let token = match value_type {
             ValueType::Object => {
+                if self.depth >= self.options.max_depth {
+                    return Err(Error::limit_exceeded("max_depth", self.options.max_depth));
+                }
+
                 self.depth += 1;
                 Token::ObjectStart
             }
             ValueType::String => {
-                let value = self.read_string()?;
-
-                Token::String(value)
+                let value = self.read_string_bytes()?;
+
+                match decode_utf8(value) {
+                    Ok(value) => Token::String(value),
+                    Err(value) => match self.options.utf8_policy {
+                        Utf8Policy::Strict => return Err(Error::new(ErrorKind::InvalidUtf8)),
+                        Utf8Policy::Lossy => {
+                            Token::String(String::from_utf8_lossy(&value).into_owned().into())
+                        }
+                        Utf8Policy::Bytes => Token::Bytes(value),
+                    },
+                }
             }
             ValueType::Int => {
                 let value = self.read_int()?;
 
                 Token::Int(value)
             }
+            ValueType::Float => {
+                let value = self.read_int()?;
+
+                Token::Float(f32::from_bits(value))
+            }
+            ValueType::Pointer => {
+                let value = self.read_int()?;
+
+                Token::Pointer(value)
+            }
+            ValueType::WideString => {
+                let value = self.read_wide_string()?;
+
+                Token::WideString(value)
+            }
+            ValueType::Color => {
+                let value = self.read_int()?;
+
+                Token::Color(value)
+            }
+            ValueType::UInt64 => {
+                let value = self.read_int64()?;
+
+                Token::UInt64(value)
+            }
+            ValueType::Int64 => {
+                let value = self.read_int64()?;
+
+                Token::Int64(value as i64)
+            }
         };
 
         Ok((token, NextOp::ParseKey))
     }
 
-    fn read_value_type(&mut self) -> Result<Option<ValueType>> {
+    /// Reads the type of the next entry, or the marker that ends the object instead.
+    fn read_value_type(&mut self) -> Result<Result<ValueType, EndMarker>> {
         let byte = self.read_byte()?;
 
-        if byte == OBJECT_END {
-            return Ok(None);
+        if let Some(end) = EndMarker::from_u8(byte) {
+            return Ok(Err(end));
         }
 
         let value_type = ValueType::from_u8(byte)
-            .with_context(|| format!("unknown value type: 0x{byte:02x}"))?;
+            .ok_or_else(|| Error::new(ErrorKind::UnknownValueType(byte)))?;
+
+        Ok(Ok(value_type))
+    }
+
+    fn read_key(&mut self) -> Result<Cow<'de, str>> {
+        let key_table = match &self.key_table {
+            Some(key_table) => key_table.clone(),
+            None => return self.read_string(),
+        };
+
+        let index = self.read_int()?;
+        let key = key_table.get(index as usize).ok_or_else(|| {
+            Error::new(ErrorKind::Message(format!(
+                "key index out of range: {index}"
+            )))
+        })?;
+
+        Ok(Cow::Owned(key.clone()))
+    }
+
+    fn read_string(&mut self) -> Result<Cow<'de, str>> {
+        let string = self.read_string_bytes()?;
+
+        let string = match decode_utf8(string) {
+            Ok(string) => string,
+            Err(_) if self.options.utf8_policy == Utf8Policy::Strict => {
+                return Err(Error::new(ErrorKind::InvalidUtf8))
+            }
+            Err(string) => String::from_utf8_lossy(&string).into_owned().into(),
+        };
+
+        Ok(string)
+    }
+
+    fn read_string_bytes(&mut self) -> Result<Cow<'de, [u8]>> {
+        let string = self.reader.read_string(self.options.max_string_len)?;
+
+        // Account for the terminator
+        self.position += string.len() as u64 + 1;
+
+        Ok(string)
+    }
+
+    fn skip_string(&mut self) -> Result<()> {
+        let len = self.reader.skip_string(self.options.max_string_len)?;
 
-        Ok(Some(value_type))
+        // Account for the terminator
+        self.position += len as u64 + 1;
+
+        Ok(())
     }
 
-    fn read_string(&mut self) -> Result<String> {
+    fn read_wide_string(&mut self) -> Result<String> {
+        let string = self.read_wide_string_units()?;
+        let string = String::from_utf16(&string).map_err(|_| Error::new(ErrorKind::InvalidUtf8))?;
+
+        Ok(string)
+    }
+
+    fn read_wide_string_units(&mut self) -> Result<Vec<u16>> {
         let mut string = Vec::new();
 
-        self.reader.read_until(STRING_END, &mut string)?;
+        loop {
+            let mut buf = [0; 2];
 
-        string.pop();
+            self.read_exact(&mut buf)?;
 
-        let string = String::from_utf8(string)?;
+            let unit = u16::from_le_bytes(buf);
+
+            if unit == 0 {
+                break;
+            }
+
+            if (string.len() + 1) * 2 > self.options.max_string_len {
+                return Err(Error::limit_exceeded(
+                    "max_string_len",
+                    self.options.max_string_len,
+                ));
+            }
+
+            string.push(unit);
+        }
 
         Ok(string)
     }
@@ -125,34 +477,111 @@ where
     fn read_int(&mut self) -> Result<u32> {
         let mut buf = [0; 4];
 
-        self.reader.read_exact(&mut buf)?;
+        self.read_exact(&mut buf)?;
 
         let n = u32::from_le_bytes(buf);
 
         Ok(n)
     }
 
+    fn read_int64(&mut self) -> Result<u64> {
+        let mut buf = [0; 8];
+
+        self.read_exact(&mut buf)?;
+
+        let n = u64::from_le_bytes(buf);
+
+        Ok(n)
+    }
+
     fn read_byte(&mut self) -> Result<u8> {
         let mut buf = [0];
 
-        self.reader.read_exact(&mut buf)?;
+        self.read_exact(&mut buf)?;
 
         Ok(buf[0])
     }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.reader.read_exact(buf)?;
+        self.position += buf.len() as u64;
+
+        Ok(())
+    }
+}
+
+/// How to handle strings that are not valid UTF-8,
+/// as written by old Steam clients or third party tools.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Utf8Policy {
+    /// Fail with `ErrorKind::InvalidUtf8`.
+    #[default]
+    Strict,
+    /// Replace invalid sequences with U+FFFD.
+    Lossy,
+    /// Emit invalid string values as `Token::Bytes`.
+    /// Keys are still replaced lossily.
+    Bytes,
+}
+
+/// Checks that `bytes` are UTF-8 without copying them,
+/// handing them back unchanged if they are not.
+fn decode_utf8(bytes: Cow<[u8]>) -> Result<Cow<str>, Cow<[u8]>> {
+    match bytes {
+        Cow::Borrowed(bytes) => match str::from_utf8(bytes) {
+            Ok(string) => Ok(Cow::Borrowed(string)),
+            Err(_) => Err(Cow::Borrowed(bytes)),
+        },
+        Cow::Owned(bytes) => match String::from_utf8(bytes) {
+            Ok(string) => Ok(Cow::Owned(string)),
+            Err(err) => Err(Cow::Owned(err.into_bytes())),
+        },
+    }
 }
 
 #[derive(Copy, Clone)]
-enum NextOp {
+pub(super) enum NextOp {
     DoNothing,
     ParseValue(ValueType),
     ParseKey,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub enum Token {
+/// A single piece of binary vdf input.
+///
+/// Keys and strings borrow from the input where the reader allows it,
+/// see `TokenDeserializer::from_slice`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token<'a> {
     ObjectStart,
-    ObjectEnd,
-    Key(String),
+    ObjectEnd(EndMarker),
+    Key(Cow<'a, str>),
     Int(u32),
-    String(String),
+    String(Cow<'a, str>),
+    Float(f32),
+    Pointer(u32),
+    WideString(String),
+    Color(u32),
+    UInt64(u64),
+    Int64(i64),
+    /// A string value that is not valid UTF-8, see `Utf8Policy::Bytes`.
+    Bytes(Cow<'a, [u8]>),
+}
+
+impl Token<'_> {
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::ObjectStart => Token::ObjectStart,
+            Token::ObjectEnd(end) => Token::ObjectEnd(end),
+            Token::Key(key) => Token::Key(Cow::Owned(key.into_owned())),
+            Token::Int(value) => Token::Int(value),
+            Token::String(value) => Token::String(Cow::Owned(value.into_owned())),
+            Token::Float(value) => Token::Float(value),
+            Token::Pointer(value) => Token::Pointer(value),
+            Token::WideString(value) => Token::WideString(value),
+            Token::Color(value) => Token::Color(value),
+            Token::UInt64(value) => Token::UInt64(value),
+            Token::Int64(value) => Token::Int64(value),
+            Token::Bytes(value) => Token::Bytes(Cow::Owned(value.into_owned())),
+        }
+    }
 }
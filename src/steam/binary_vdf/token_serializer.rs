@@ -1,10 +1,9 @@
 use std::io::Write;
 
-use serde::{de, ser};
+use serde::ser;
 
-use super::{ValueType, OBJECT_END, STRING_END};
-
-type Result<T, E = de::value::Error> = std::result::Result<T, E>;
+use super::error::Result;
+use super::{EndMarker, ValueType, STRING_END};
 
 pub struct TokenSerializer<W> {
     writer: W,
@@ -26,6 +25,11 @@ where
         }
     }
 
+    #[cfg(feature = "tokio")]
+    pub(super) fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
     pub fn emit_object_start(&mut self) -> Result<()> {
         self.assert(Expected::Value)?;
 
@@ -47,10 +51,10 @@ where
         Ok(())
     }
 
-    pub fn emit_object_end(&mut self) -> Result<()> {
+    pub fn emit_object_end(&mut self, end: EndMarker) -> Result<()> {
         self.assert(Expected::KeyOrEndObject)?;
 
-        self.write_end_object()?;
+        self.write_end_object(end)?;
 
         self.depth -= 1;
 
@@ -81,6 +85,96 @@ where
         Ok(())
     }
 
+    /// Drops the pending key, so that an absent value leaves no trace.
+    pub fn emit_none(&mut self) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        if self.current_key.take().is_none() {
+            return Err(ser::Error::custom("binary vdf root must be an object"));
+        }
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    pub fn emit_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        self.write_current_key(ValueType::String)?;
+        self.write_bytes(bytes)?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    pub fn emit_float(&mut self, value: f32) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        self.write_current_key(ValueType::Float)?;
+        self.write_int(value.to_bits())?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    pub fn emit_pointer(&mut self, value: u32) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        self.write_current_key(ValueType::Pointer)?;
+        self.write_int(value)?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    pub fn emit_wide_string(&mut self, string: &str) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        self.write_current_key(ValueType::WideString)?;
+        self.write_wide_string(string)?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    pub fn emit_color(&mut self, value: u32) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        self.write_current_key(ValueType::Color)?;
+        self.write_int(value)?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    pub fn emit_uint64(&mut self, value: u64) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        self.write_current_key(ValueType::UInt64)?;
+        self.write_int64(value)?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    pub fn emit_int64(&mut self, value: i64) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        self.write_current_key(ValueType::Int64)?;
+        self.write_int64(value as u64)?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
     fn end_of_value(&mut self) {
         if self.depth == 0 {
             self.expected = Expected::End;
@@ -103,6 +197,9 @@ where
     fn write_current_key(&mut self, value_type: ValueType) -> Result<()> {
         let key = match self.current_key.take() {
             Some(key) => key,
+            None if self.depth == 0 => {
+                return Err(ser::Error::custom("binary vdf root must be an object"))
+            }
             None => return Ok(()),
         };
 
@@ -113,38 +210,54 @@ where
     }
 
     fn write_value_type(&mut self, value_type: ValueType) -> Result<()> {
-        self.writer.write_all(&[value_type as u8]).map_err(ser::Error::custom)?;
+        self.writer.write_all(&[value_type as u8])?;
 
         Ok(())
     }
 
     fn write_string(&mut self, value: &str) -> Result<()> {
-        if value.as_bytes().contains(&STRING_END) {
+        self.write_bytes(value.as_bytes())
+    }
+
+    fn write_bytes(&mut self, value: &[u8]) -> Result<()> {
+        if value.contains(&STRING_END) {
             return Err(ser::Error::custom("string must not contain NULL"));
         }
 
-        self.writer
-            .write_all(value.as_bytes())
-            .map_err(ser::Error::custom)?;
-        self.writer
-            .write_all(&[STRING_END])
-            .map_err(ser::Error::custom)?;
+        self.writer.write_all(value)?;
+        self.writer.write_all(&[STRING_END])?;
+
+        Ok(())
+    }
+
+    fn write_wide_string(&mut self, value: &str) -> Result<()> {
+        for unit in value.encode_utf16() {
+            if unit == 0 {
+                return Err(ser::Error::custom("string must not contain NULL"));
+            }
+
+            self.writer.write_all(&unit.to_le_bytes())?;
+        }
+
+        self.writer.write_all(&0u16.to_le_bytes())?;
 
         Ok(())
     }
 
     fn write_int(&mut self, value: u32) -> Result<()> {
-        self.writer
-            .write_all(&value.to_le_bytes())
-            .map_err(ser::Error::custom)?;
+        self.writer.write_all(&value.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    fn write_int64(&mut self, value: u64) -> Result<()> {
+        self.writer.write_all(&value.to_le_bytes())?;
 
         Ok(())
     }
 
-    fn write_end_object(&mut self) -> Result<()> {
-        self.writer
-            .write_all(&[OBJECT_END])
-            .map_err(ser::Error::custom)?;
+    fn write_end_object(&mut self, end: EndMarker) -> Result<()> {
+        self.writer.write_all(&[end.to_u8()])?;
 
         Ok(())
     }
@@ -0,0 +1,339 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use indexmap::IndexMap;
+use serde::de::{self, DeserializeOwned, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+
+use super::error::Result;
+use super::{from_bytes, to_bytes, EndMarker, Object};
+
+// Value types and end markers that serde has no native equivalent for are passed
+// through the binary vdf (de)serializer under these private names,
+// so that they survive a round-trip through `Value` unchanged.
+// Other formats only ever see the inner value.
+pub(super) const VALUE_TOKEN: &str = "$binary_vdf::Value";
+pub(super) const POINTER_TOKEN: &str = "$binary_vdf::Pointer";
+pub(super) const WIDE_STRING_TOKEN: &str = "$binary_vdf::WideString";
+pub(super) const COLOR_TOKEN: &str = "$binary_vdf::Color";
+pub(super) const OBJECT_END_ALT_TOKEN: &str = "$binary_vdf::ObjectEndAlt";
+
+/// Converts a `Value` into any deserializable type.
+///
+/// Like binary vdf documents themselves, the value must be an object.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    let bytes = to_bytes(&value)?;
+
+    from_bytes(&bytes)
+}
+
+/// Converts any serializable type into a `Value`.
+///
+/// Like binary vdf documents themselves, the value must serialize to an object.
+pub fn to_value<T: Serialize>(value: &T) -> Result<Value> {
+    let bytes = to_bytes(value)?;
+
+    from_bytes(&bytes)
+}
+
+/// A binary vdf document or any value within it.
+///
+/// Reading a document into a `Value` and writing it back produces the
+/// exact same bytes. Documents with duplicate keys in an object
+/// can't be read into a `Value`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Object(Object),
+    String(String),
+    Int(u32),
+    Float(f32),
+    Pointer(u32),
+    WideString(String),
+    Color(u32),
+    UInt64(u64),
+    Int64(i64),
+    /// A string that is not valid UTF-8, see `Utf8Policy::Bytes`.
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, Value::Object(_))
+    }
+
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Object> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of both `String` and `WideString` values.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) | Value::WideString(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<u32> {
+        match *self {
+            Value::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f32> {
+        match *self {
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_pointer(&self) -> Option<u32> {
+        match *self {
+            Value::Pointer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<u32> {
+        match *self {
+            Value::Color(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_uint64(&self) -> Option<u64> {
+        match *self {
+            Value::UInt64(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int64(&self) -> Option<i64> {
+        match *self {
+            Value::Int64(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw contents of `String` and `Bytes` values.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::String(string) => Some(string.as_bytes()),
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("no entry found for key {key:?}"),
+        }
+    }
+}
+
+impl IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        match self.get_mut(key) {
+            Some(value) => value,
+            None => panic!("no entry found for key {key:?}"),
+        }
+    }
+}
+
+impl From<Object> for Value {
+    fn from(object: Object) -> Self {
+        Value::Object(object)
+    }
+}
+
+impl From<IndexMap<String, Value>> for Value {
+    fn from(object: IndexMap<String, Value>) -> Self {
+        Value::Object(object.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Value::String(string)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Value::String(string.to_string())
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Value::Object(object) => object.serialize(serializer),
+            Value::String(value) => serializer.serialize_str(value),
+            Value::Int(value) => serializer.serialize_u32(*value),
+            Value::Float(value) => serializer.serialize_f32(*value),
+            Value::Pointer(value) => serializer.serialize_newtype_struct(POINTER_TOKEN, value),
+            Value::WideString(value) => {
+                serializer.serialize_newtype_struct(WIDE_STRING_TOKEN, value)
+            }
+            Value::Color(value) => serializer.serialize_newtype_struct(COLOR_TOKEN, value),
+            Value::UInt64(value) => serializer.serialize_u64(*value),
+            Value::Int64(value) => serializer.serialize_i64(*value),
+            Value::Bytes(value) => serializer.serialize_bytes(value),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a binary vdf value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Int(value as u32))
+    }
+
+    fn visit_i32<E>(self, value: i32) -> Result<Value, E> {
+        Ok(Value::Int(value as u32))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Int64(value))
+    }
+
+    fn visit_u32<E>(self, value: u32) -> Result<Value, E> {
+        Ok(Value::Int(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::UInt64(value))
+    }
+
+    fn visit_f32<E>(self, value: f32) -> Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Float(value as f32))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(value))
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut object = Object::new();
+
+        while let Some(value) = seq.next_element()? {
+            let key = object.len().to_string();
+            object.insert(key, value);
+        }
+
+        Ok(Value::Object(object))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut object = Object::new();
+
+        let mut next_key = map.next_key::<String>()?;
+
+        match next_key.as_deref() {
+            Some(POINTER_TOKEN) => return Ok(Value::Pointer(map.next_value()?)),
+            Some(WIDE_STRING_TOKEN) => return Ok(Value::WideString(map.next_value()?)),
+            Some(COLOR_TOKEN) => return Ok(Value::Color(map.next_value()?)),
+            _ => {}
+        }
+
+        while let Some(key) = next_key {
+            if key == OBJECT_END_ALT_TOKEN {
+                map.next_value::<IgnoredAny>()?;
+                object.set_end_marker(EndMarker::Alternate);
+            } else if object.contains_key(&key) {
+                return Err(de::Error::custom(format!("duplicate key `{key}`")));
+            } else {
+                let value = map.next_value()?;
+                object.insert(key, value);
+            }
+
+            next_key = map.next_key()?;
+        }
+
+        Ok(Value::Object(object))
+    }
+}
@@ -0,0 +1,402 @@
+//! Lossless conversion between binary vdf, text vdf and JSON.
+//!
+//! Text vdf only knows strings and objects, and JSON numbers cannot tell
+//! the many integer types of binary vdf apart, so every value other than
+//! a string or an object is annotated with its type. It is written as an
+//! object with a single entry, keyed by the type:
+//!
+//! ```text
+//! "appid"
+//! {
+//!     "$int"      "3735928559"
+//! }
+//! ```
+//!
+//! or `"appid": { "$int": "3735928559" }` in JSON. The annotations are
+//! `$int`, `$float`, `$pointer`, `$wide_string`, `$color`, `$uint64`,
+//! `$int64` and `$bytes`, the latter holding the bytes of a string that
+//! is not valid UTF-8 in hex.
+//!
+//! Plain numbers are accepted in JSON as well. Integers become `$int` if
+//! they fit into one, `$int64` if they are negative and `$uint64` otherwise,
+//! and numbers with a fraction or exponent become `$float`.
+//!
+//! An object that ends with the alternate marker `0x0B` instead of `0x08`
+//! is wrapped as `{ "$object_alt": { ... } }`. Any other object that itself
+//! consists of a single entry keyed like an annotation is wrapped as
+//! `{ "$object": { ... } }`, so that binary vdf documents survive the trip
+//! through text or JSON unchanged.
+//!
+//! Binary vdf objects cannot hold a key twice, so documents in any format
+//! that repeat a key within an object are rejected rather than losing one
+//! of its values.
+
+use std::{fmt, mem, str};
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use serde::de::{self, Deserialize, MapAccess, SeqAccess, Visitor};
+
+use super::binary_vdf::{self, DeserializerOptions, EndMarker, Utf8Policy};
+use super::text_vdf::{self, Object};
+
+const OBJECT: &str = "$object";
+const OBJECT_ALT: &str = "$object_alt";
+const INT: &str = "$int";
+const FLOAT: &str = "$float";
+const POINTER: &str = "$pointer";
+const WIDE_STRING: &str = "$wide_string";
+const COLOR: &str = "$color";
+const UINT64: &str = "$uint64";
+const INT64: &str = "$int64";
+const BYTES: &str = "$bytes";
+
+const ANNOTATIONS: &[&str] = &[
+    OBJECT,
+    OBJECT_ALT,
+    INT,
+    FLOAT,
+    POINTER,
+    WIDE_STRING,
+    COLOR,
+    UINT64,
+    INT64,
+    BYTES,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Binary,
+    Text,
+    Json,
+}
+
+/// Converts a document from one format into another, e.g. `shortcuts.vdf` into JSON.
+pub fn convert(input: &[u8], from: Format, to: Format) -> Result<Vec<u8>> {
+    let value = read(input, from)?;
+
+    write(&value, to)
+}
+
+/// Reads a document in any format into a binary vdf `Value`.
+///
+/// Text vdf conditionals are evaluated for the current platform.
+/// Keys repeated within an object are an error.
+pub fn read(input: &[u8], format: Format) -> Result<binary_vdf::Value> {
+    match format {
+        Format::Binary => {
+            let value = DeserializerOptions::new()
+                .utf8_policy(Utf8Policy::Bytes)
+                .from_bytes(input)
+                .context("failed to read binary vdf")?;
+
+            Ok(value)
+        }
+        Format::Text => {
+            let input = str::from_utf8(input).context("text vdf must be UTF-8")?;
+            let value = text_vdf::from_str(input).context("failed to read text vdf")?;
+
+            from_text_value(value)
+        }
+        Format::Json => {
+            let value =
+                serde_json::from_slice::<JsonValue>(input).context("failed to read JSON")?;
+
+            from_text_value(value.into_value())
+        }
+    }
+}
+
+/// Writes a binary vdf `Value`, which must be an object, in any format.
+pub fn write(value: &binary_vdf::Value, format: Format) -> Result<Vec<u8>> {
+    if !value.is_object() {
+        bail!("only objects can be written as a document");
+    }
+
+    let bytes = match format {
+        Format::Binary => binary_vdf::to_bytes(value).context("failed to write binary vdf")?,
+        Format::Text => text_vdf::to_string(&to_text_value(value))
+            .context("failed to write text vdf")?
+            .into_bytes(),
+        Format::Json => {
+            serde_json::to_vec_pretty(&to_text_value(value)).context("failed to write JSON")?
+        }
+    };
+
+    Ok(bytes)
+}
+
+/// Converts a binary vdf `Value` into a text vdf `Value`, annotating the types
+/// of values that are neither strings nor objects.
+pub fn to_text_value(value: &binary_vdf::Value) -> text_vdf::Value {
+    use binary_vdf::Value;
+
+    let (annotation, string) = match value {
+        Value::Object(object) => {
+            let object_end = object.end_marker();
+            let object = object
+                .iter()
+                .map(|(key, value)| (key.as_str(), to_text_value(value)))
+                .collect::<Object>();
+
+            if object_end == EndMarker::Alternate {
+                return annotated(OBJECT_ALT, object.into());
+            }
+
+            return match split_annotation(object) {
+                Ok((annotation, value)) => annotated(OBJECT, annotated(annotation, value)),
+                Err(object) => object.into(),
+            };
+        }
+        Value::String(string) => return text_vdf::Value::String(string.clone()),
+        Value::Int(value) => (INT, value.to_string()),
+        Value::Float(value) => (FLOAT, value.to_string()),
+        Value::Pointer(value) => (POINTER, value.to_string()),
+        Value::WideString(string) => (WIDE_STRING, string.clone()),
+        Value::Color(value) => (COLOR, value.to_string()),
+        Value::UInt64(value) => (UINT64, value.to_string()),
+        Value::Int64(value) => (INT64, value.to_string()),
+        Value::Bytes(bytes) => (
+            BYTES,
+            bytes.iter().map(|byte| format!("{byte:02x}")).collect(),
+        ),
+    };
+
+    annotated(annotation, string.into())
+}
+
+/// Converts a text vdf `Value` with type annotations back into a binary vdf `Value`.
+///
+/// Values without annotations become strings.
+pub fn from_text_value(value: text_vdf::Value) -> Result<binary_vdf::Value> {
+    from_text_value_at(value, &mut Vec::new())
+}
+
+fn from_text_value_at(value: text_vdf::Value, path: &mut Vec<String>) -> Result<binary_vdf::Value> {
+    use binary_vdf::Value;
+
+    let object = match value {
+        text_vdf::Value::String(string) => return Ok(Value::String(string)),
+        text_vdf::Value::Object(object) => object,
+    };
+
+    let (annotation, value) = match split_annotation(object) {
+        Ok(annotated) => annotated,
+        Err(object) => return from_text_object(object, path),
+    };
+
+    let string = match value {
+        text_vdf::Value::Object(object) if annotation == OBJECT => {
+            return from_text_object(object, path)
+        }
+        text_vdf::Value::Object(object) if annotation == OBJECT_ALT => {
+            let mut converted = from_text_object(object, path)?;
+
+            if let Some(object) = converted.as_object_mut() {
+                object.set_end_marker(EndMarker::Alternate);
+            }
+
+            return Ok(converted);
+        }
+        text_vdf::Value::String(string) if annotation != OBJECT && annotation != OBJECT_ALT => {
+            string
+        }
+        _ => bail!("invalid value for {annotation} at `{}`", path.join(".")),
+    };
+
+    let parsed = match annotation {
+        INT => string.parse().map(Value::Int).ok(),
+        FLOAT => string.parse().map(Value::Float).ok(),
+        POINTER => string.parse().map(Value::Pointer).ok(),
+        WIDE_STRING => return Ok(Value::WideString(string)),
+        COLOR => string.parse().map(Value::Color).ok(),
+        UINT64 => string.parse().map(Value::UInt64).ok(),
+        INT64 => string.parse().map(Value::Int64).ok(),
+        BYTES => parse_hex(&string).map(Value::Bytes),
+        _ => unreachable!("only known annotations are matched"),
+    };
+
+    parsed.with_context(|| format!("invalid {annotation} {string:?} at `{}`", path.join(".")))
+}
+
+fn from_text_object(object: Object, path: &mut Vec<String>) -> Result<binary_vdf::Value> {
+    let mut converted = IndexMap::with_capacity(object.len());
+
+    for (key, value) in object {
+        let duplicate = converted.contains_key(&key);
+
+        path.push(key);
+
+        if duplicate {
+            let key = path.last().expect("key was pushed");
+            bail!("duplicate key `{key}` at `{}`", path.join("."));
+        }
+
+        let value = from_text_value_at(value, path)?;
+        let key = path.pop().expect("key was pushed");
+
+        converted.insert(key, value);
+    }
+
+    Ok(binary_vdf::Value::Object(converted.into()))
+}
+
+fn annotated(annotation: &str, value: text_vdf::Value) -> text_vdf::Value {
+    text_vdf::Value::Object(Object::from_iter([(annotation, value)]))
+}
+
+/// Splits an object consisting of a single annotation into the annotation and its value.
+fn split_annotation(object: Object) -> Result<(&'static str, text_vdf::Value), Object> {
+    if object.len() != 1 {
+        return Err(object);
+    }
+
+    let annotation = object.keys().next().and_then(|key| {
+        ANNOTATIONS
+            .iter()
+            .copied()
+            .find(|annotation| *annotation == key)
+    });
+
+    match annotation {
+        Some(annotation) => {
+            let (_, value) = object.into_iter().next().expect("object has one entry");
+            Ok((annotation, value))
+        }
+        None => Err(object),
+    }
+}
+
+fn parse_hex(string: &str) -> Option<Vec<u8>> {
+    if !string.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..string.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(string.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A JSON value, to be read as a text vdf `Value` with plain numbers annotated with their type.
+enum JsonValue {
+    String(String),
+    Number(&'static str, String),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    fn into_value(self) -> text_vdf::Value {
+        let mut entries = match self {
+            JsonValue::String(string) => return string.into(),
+            JsonValue::Number(annotation, number) => return annotated(annotation, number.into()),
+            JsonValue::Object(entries) => entries,
+        };
+
+        if let [(key, value)] = entries.as_mut_slice() {
+            match value {
+                // The number of an annotation, e.g. `{ "$int": 10 }`, is its value as written
+                JsonValue::Number(_, number)
+                    if key != OBJECT
+                        && key != OBJECT_ALT
+                        && ANNOTATIONS.contains(&key.as_str()) =>
+                {
+                    return annotated(key, mem::take(number).into());
+                }
+                // An object wrapped in `$object` or `$object_alt` is not an annotation itself
+                JsonValue::Object(object) if key == OBJECT || key == OBJECT_ALT => {
+                    return annotated(key, Self::object(mem::take(object)));
+                }
+                _ => {}
+            }
+        }
+
+        Self::object(entries)
+    }
+
+    fn object(entries: Vec<(String, JsonValue)>) -> text_vdf::Value {
+        entries
+            .into_iter()
+            .map(|(key, value)| (key, value.into_value()))
+            .collect::<Object>()
+            .into()
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(JsonVisitor)
+    }
+}
+
+struct JsonVisitor;
+
+impl<'de> Visitor<'de> for JsonVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::String((value as u8).to_string()))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<JsonValue, E> {
+        match u32::try_from(value) {
+            Ok(value) => Ok(JsonValue::Number(INT, value.to_string())),
+            Err(_) => Ok(JsonValue::Number(INT64, value.to_string())),
+        }
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<JsonValue, E> {
+        match u32::try_from(value) {
+            Ok(value) => Ok(JsonValue::Number(INT, value.to_string())),
+            Err(_) => Ok(JsonValue::Number(UINT64, value.to_string())),
+        }
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(FLOAT, value.to_string()))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(value))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<JsonValue, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut entries = Vec::new();
+
+        while let Some(value) = seq.next_element()? {
+            entries.push((entries.len().to_string(), value));
+        }
+
+        Ok(JsonValue::Object(entries))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<JsonValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::new();
+
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+
+        Ok(JsonValue::Object(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,215 @@
+use indexmap::IndexMap;
+
+use super::{convert, from_text_value, read, to_text_value, write, Format};
+use crate::steam::binary_vdf::{self, Value};
+use crate::steam::text_vdf;
+
+fn object<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Object(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect::<IndexMap<_, _>>()
+            .into(),
+    )
+}
+
+fn every_type() -> Value {
+    object([
+        (
+            "shortcuts",
+            object([(
+                "0",
+                object([
+                    ("appid", Value::Int(3735928559)),
+                    ("AppName", Value::String("Quoted \"name\"\t\\".to_string())),
+                    ("LastPlayTime", Value::Int(0)),
+                    ("tags", object([])),
+                ]),
+            )]),
+        ),
+        ("float", Value::Float(-0.1)),
+        ("pointer", Value::Pointer(7)),
+        ("wide", Value::WideString("wide".to_string())),
+        ("color", Value::Color(0xff00ff00)),
+        ("uint64", Value::UInt64(u64::MAX)),
+        ("int64", Value::Int64(i64::MIN)),
+        ("bytes", Value::Bytes(vec![0xff, 0xfe, 0x41])),
+        ("numeric string", Value::String("1".to_string())),
+        (
+            "$basetexture",
+            Value::String("not an annotation".to_string()),
+        ),
+        (
+            "lookalike",
+            object([("$int", Value::String("1".to_string()))]),
+        ),
+        ("nested lookalike", object([("$object", object([]))])),
+    ])
+}
+
+#[test]
+fn binary_round_trips() {
+    let bytes = binary_vdf::to_bytes(&every_type()).unwrap();
+
+    for format in [Format::Binary, Format::Text, Format::Json] {
+        let converted = convert(&bytes, Format::Binary, format).unwrap();
+        let round_tripped = convert(&converted, format, Format::Binary).unwrap();
+
+        assert_eq!(round_tripped, bytes, "{format:?}");
+    }
+}
+
+#[test]
+fn binary_fixture_round_trips() {
+    // Objects ending with 0x0B, one of them holding an annotation lookalike
+    let bytes = [
+        b"\x00a\x00".as_slice(),
+        b"\x02b\x00\x01\x00\x00\x00",
+        b"\x0B",
+        b"\x00lookalike\x00\x01$int\x001\x00\x0B",
+        b"\x00empty\x00\x0B",
+        b"\x01c\x00d\x00",
+        b"\x08",
+    ]
+    .concat();
+
+    for format in [Format::Binary, Format::Text, Format::Json] {
+        let converted = convert(&bytes, Format::Binary, format).unwrap();
+        let round_tripped = convert(&converted, format, Format::Binary).unwrap();
+
+        assert_eq!(round_tripped, bytes, "{format:?}");
+    }
+
+    let json = convert(&bytes, Format::Binary, Format::Json).unwrap();
+    let value = serde_json::from_slice::<serde_json::Value>(&json).unwrap();
+    assert_eq!(
+        value["a"],
+        serde_json::json!({ "$object_alt": { "b": { "$int": "1" } } })
+    );
+
+    // Keys spelled twice would lose a value, so they are rejected in every format
+    let duplicate = b"\x01a\x00x\x00\x01a\x00y\x00\x08";
+    let err = convert(duplicate, Format::Binary, Format::Text).unwrap_err();
+    assert!(format!("{err:#}").contains("duplicate key `a`"), "{err:#}");
+
+    let err = read(br#""a" { "b" "x" "b" { } }"#, Format::Text).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key `b` at `a.b`");
+
+    let err = read(
+        br#"{ "a": { "$object_alt": { "b": 1, "b": 2 } } }"#,
+        Format::Json,
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "duplicate key `b` at `a.b`");
+}
+
+#[test]
+fn text_and_json_convert_into_each_other() {
+    let json = write(&every_type(), Format::Json).unwrap();
+    let text = convert(&json, Format::Json, Format::Text).unwrap();
+
+    assert_eq!(read(&text, Format::Text).unwrap(), every_type());
+    assert_eq!(convert(&text, Format::Text, Format::Json).unwrap(), json);
+}
+
+#[test]
+fn annotations() {
+    let value = object([
+        ("name", Value::String("a".to_string())),
+        ("appid", Value::Int(10)),
+        ("lookalike", object([("$int", Value::Int(1))])),
+    ]);
+
+    let text = text_vdf::to_string(&to_text_value(&value)).unwrap();
+
+    assert_eq!(
+        text,
+        r#""name"		"a"
+"appid"
+{
+	"$int"		"10"
+}
+"lookalike"
+{
+	"$object"
+	{
+		"$int"
+		{
+			"$int"		"1"
+		}
+	}
+}
+"#
+    );
+
+    assert_eq!(
+        from_text_value(text_vdf::from_str(&text).unwrap()).unwrap(),
+        value
+    );
+}
+
+#[test]
+fn json_numbers() {
+    let json = br#"{ "appid": { "$int": 10 }, "ratio": { "$float": 0.5 }, "count": 3 }"#;
+
+    assert_eq!(
+        read(json, Format::Json).unwrap(),
+        object([
+            ("appid", Value::Int(10)),
+            ("ratio", Value::Float(0.5)),
+            ("count", Value::Int(3)),
+        ])
+    );
+
+    let json = br#"{
+        "int": 4294967295,
+        "uint64": 4294967296,
+        "int64": -1,
+        "float": 1.5,
+        "exponent": 1e2,
+        "nested": { "a": [0, "1"] },
+        "wrapped": { "$object": { "$int": 2 } },
+        "$int": 3,
+        "other": "x"
+    }"#;
+
+    assert_eq!(
+        read(json, Format::Json).unwrap(),
+        object([
+            ("int", Value::Int(u32::MAX)),
+            ("uint64", Value::UInt64(1 << 32)),
+            ("int64", Value::Int64(-1)),
+            ("float", Value::Float(1.5)),
+            ("exponent", Value::Float(100.0)),
+            (
+                "nested",
+                object([(
+                    "a",
+                    object([("0", Value::Int(0)), ("1", Value::String("1".to_string()))])
+                )])
+            ),
+            ("wrapped", object([("$int", Value::Int(2))])),
+            ("$int", Value::Int(3)),
+            ("other", Value::String("x".to_string())),
+        ])
+    );
+
+    let err = read(br#"{ "a": { "$int": 0.5 } }"#, Format::Json).unwrap_err();
+    assert_eq!(err.to_string(), r#"invalid $int "0.5" at `a`"#);
+}
+
+#[test]
+fn invalid_annotations() {
+    let err = read(br#""a" { "b" { "$int" "ten" } }"#, Format::Text).unwrap_err();
+    assert_eq!(err.to_string(), r#"invalid $int "ten" at `a.b`"#);
+
+    let err = read(br#""a" { "$bytes" "abc" }"#, Format::Text).unwrap_err();
+    assert_eq!(err.to_string(), r#"invalid $bytes "abc" at `a`"#);
+
+    let err = read(br#""a" { "$uint64" { } }"#, Format::Text).unwrap_err();
+    assert_eq!(err.to_string(), "invalid value for $uint64 at `a`");
+
+    let err = write(&Value::Int(1), Format::Text).unwrap_err();
+    assert_eq!(err.to_string(), "only objects can be written as a document");
+}
@@ -44,6 +44,12 @@ impl From<AppId> for u32 {
     }
 }
 
+impl From<u32> for AppId {
+    fn from(app_id: u32) -> AppId {
+        Self(app_id)
+    }
+}
+
 impl From<LegacyAppId> for AppId {
     fn from(legacy_app_id: LegacyAppId) -> AppId {
         let legacy_app_id = u64::from(legacy_app_id);
@@ -0,0 +1,23 @@
+pub use self::{
+    de::{from_str, from_token_deserializer},
+    error::{Error, ErrorKind, Result},
+    header::Header,
+    ser::{to_string, to_writer, to_writer_with_header},
+    token_deserializer::TokenDeserializer,
+    token_serializer::TokenSerializer,
+    value::{from_value, to_value, Flag, Value},
+};
+
+mod de;
+mod error;
+mod header;
+mod ser;
+pub mod token_deserializer;
+mod token_serializer;
+mod value;
+
+#[cfg(test)]
+mod tests;
+
+/// How deeply objects and arrays may be nested, including the root value.
+const MAX_DEPTH: usize = 256;
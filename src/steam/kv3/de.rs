@@ -0,0 +1,380 @@
+use std::borrow::Cow;
+
+use serde::de::value::{BorrowedStrDeserializer, SeqDeserializer};
+use serde::de::{
+    self, Deserialize, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+};
+use serde::forward_to_deserialize_any;
+
+use super::error::{Error, ErrorKind, Result};
+use super::token_deserializer::{Token, TokenDeserializer};
+use super::value::VALUE_TOKEN;
+use super::Flag;
+use crate::serde_utils::KeyDeserializer;
+
+/// Deserializes a KV3 text document, borrowing from `input` where possible.
+///
+/// Flags are ignored unless deserializing into a `Value`,
+/// so `resource:"models/a.vmdl"` reads like any other string.
+pub fn from_str<'de, D: Deserialize<'de>>(input: &'de str) -> Result<D> {
+    from_token_deserializer(TokenDeserializer::new(input))
+}
+
+pub fn from_token_deserializer<'de, D: Deserialize<'de>>(
+    token_deserializer: TokenDeserializer<'de>,
+) -> Result<D> {
+    let mut de = Deserializer {
+        token_deserializer,
+        peeked: None,
+    };
+
+    let result = D::deserialize(&mut de).and_then(|value| de.expect_end().map(|()| value));
+
+    result.map_err(|err| de.token_deserializer.locate(err))
+}
+
+struct Deserializer<'de> {
+    token_deserializer: TokenDeserializer<'de>,
+    peeked: Option<Token<'de>>,
+}
+
+impl<'de> Deserializer<'de> {
+    fn expect_token(&mut self) -> Result<Token<'de>> {
+        if let Some(token) = self.peeked.take() {
+            return Ok(token);
+        }
+
+        match self.token_deserializer.next() {
+            Some(token) => token,
+            None => Err(Error::new(ErrorKind::UnexpectedEof)),
+        }
+    }
+
+    fn peek_token(&mut self) -> Result<&Token<'de>> {
+        let token = match self.peeked.take() {
+            Some(token) => token,
+            None => self.expect_token()?,
+        };
+
+        Ok(self.peeked.insert(token))
+    }
+
+    /// Reads the next token that is not a flag.
+    fn expect_value(&mut self) -> Result<Token<'de>> {
+        loop {
+            match self.expect_token()? {
+                Token::Flag(_) => {}
+                token => return Ok(token),
+            }
+        }
+    }
+
+    fn expect_end(&mut self) -> Result<()> {
+        if let Some(token) = self.peeked.take() {
+            return unexpected_token("end of input", token);
+        }
+
+        match self.token_deserializer.next() {
+            None => Ok(()),
+            Some(token) => unexpected_token("end of input", token?),
+        }
+    }
+
+    fn deserialize_token<V>(&mut self, token: Token<'de>, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match token {
+            Token::ObjectStart => visitor.visit_map(self),
+            Token::ArrayStart => {
+                let mut seq = Seq {
+                    de: &mut *self,
+                    end: false,
+                };
+                let value = visitor.visit_seq(&mut seq)?;
+
+                // Fixed size sequences like tuples stop reading after their last element
+                if !seq.end {
+                    match self.expect_token()? {
+                        Token::ArrayEnd => {}
+                        token => return unexpected_token("end of array", token),
+                    }
+                }
+
+                Ok(value)
+            }
+            Token::Null => visitor.visit_unit(),
+            Token::Bool(value) => visitor.visit_bool(value),
+            Token::Int(value) => visitor.visit_i64(value),
+            Token::UInt(value) => visitor.visit_u64(value),
+            Token::Float(value) => visitor.visit_f64(value),
+            Token::String(Cow::Borrowed(value)) => visitor.visit_borrowed_str(value),
+            Token::String(Cow::Owned(value)) => visitor.visit_string(value),
+            Token::Binary(value) => visitor.visit_byte_buf(value),
+            token => unexpected_token("value", token),
+        }
+    }
+
+    /// Presents flagged values as a map keyed by the flag, see `Value`.
+    fn deserialize_value<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let Token::Flag(flag) = *self.peek_token()? {
+            self.peeked = None;
+
+            return visitor.visit_map(Flagged {
+                de: self,
+                flag: Some(flag),
+            });
+        }
+
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+fn unexpected_token<T>(expected: &'static str, token: Token) -> Result<T> {
+    Err(Error::unexpected_token(expected, token.into_owned()))
+}
+
+impl<'de> serde::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let token = self.expect_value()?;
+
+        self.deserialize_token(token, visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let null = match self.peek_token()? {
+            Token::Null => true,
+            // Flags are only skipped for a null, as `Value` keeps them otherwise
+            Token::Flag(_) => matches!(
+                self.token_deserializer
+                    .clone()
+                    .find(|token| !matches!(token, Ok(Token::Flag(_)))),
+                Some(Ok(Token::Null))
+            ),
+            _ => false,
+        };
+
+        if null {
+            self.expect_value()?;
+            return visitor.visit_none();
+        }
+
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name == VALUE_TOKEN {
+            return self.deserialize_value(visitor);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_value()? {
+            // Allows reading binary blobs into e.g. `Vec<u8>`
+            Token::Binary(bytes) => {
+                visitor.visit_seq(SeqDeserializer::<_, Error>::new(bytes.into_iter()))
+            }
+            token => self.deserialize_token(token, visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_value()? {
+            Token::String(Cow::Borrowed(variant)) => {
+                visitor.visit_enum(BorrowedStrDeserializer::<Error>::new(variant))
+            }
+            Token::String(Cow::Owned(variant)) => {
+                visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant))
+            }
+            Token::ObjectStart => {
+                let value = visitor.visit_enum(&mut *self)?;
+
+                match self.expect_token()? {
+                    Token::ObjectEnd => Ok(value),
+                    token => unexpected_token("end of object", token),
+                }
+            }
+            token => unexpected_token("enum", token),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> MapAccess<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let key = match self.expect_token()? {
+            Token::Key(key) => key,
+            Token::ObjectEnd => return Ok(None),
+            token => return unexpected_token("key or end of object", token),
+        };
+
+        seed.deserialize(KeyDeserializer::<Error>::new(key, true))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+}
+
+struct Seq<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    end: bool,
+}
+
+impl<'de, 'a> SeqAccess<'de> for Seq<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if let Token::ArrayEnd = self.de.peek_token()? {
+            self.de.peeked = None;
+            self.end = true;
+            return Ok(None);
+        }
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+/// A flagged value as a map with a single entry, keyed by `Flag::token`.
+struct Flagged<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    flag: Option<Flag>,
+}
+
+impl<'de, 'a> MapAccess<'de> for Flagged<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.flag.take() {
+            Some(flag) => seed
+                .deserialize(IntoDeserializer::<Error>::into_deserializer(flag.token()))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        // Further flags are read by the value itself
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+impl<'de> EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.next_key_seed(seed)? {
+            Some(variant) => Ok((variant, self)),
+            None => unexpected_token("variant", Token::ObjectEnd),
+        }
+    }
+}
+
+impl<'de> VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(de::Error::custom("expected unit variant to be a string"))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::{self, Display};
+use std::io;
+
+use serde::{de, ser};
+
+use super::token_deserializer::Token;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    // Boxed to keep results small
+    location: Option<Box<Location>>,
+}
+
+#[derive(Debug)]
+struct Location {
+    offset: usize,
+    // 1-based
+    line: usize,
+    column: usize,
+    path: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    UnexpectedEof,
+    UnexpectedToken {
+        expected: &'static str,
+        actual: Token<'static>,
+    },
+    /// Input that does not make up any token, e.g. an unknown flag.
+    InvalidSyntax(String),
+    TypeMismatch {
+        expected: String,
+        actual: String,
+    },
+    LimitExceeded {
+        limit: &'static str,
+        max: usize,
+    },
+    Message(String),
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The byte offset of the token in the input at which the error occurred.
+    pub fn offset(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.offset)
+    }
+
+    /// The line of the offending token, starting at 1.
+    pub fn line(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.line)
+    }
+
+    /// The column of the offending token in characters, starting at 1.
+    pub fn column(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.column)
+    }
+
+    /// The dotted path of keys and array indices leading to the value
+    /// at which the error occurred, e.g. `m_Children.0._class`.
+    pub fn path(&self) -> Option<&str> {
+        self.location.as_ref()?.path.as_deref()
+    }
+
+    pub(super) fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            location: None,
+        }
+    }
+
+    pub(super) fn unexpected_token(expected: &'static str, actual: Token<'static>) -> Self {
+        Self::new(ErrorKind::UnexpectedToken { expected, actual })
+    }
+
+    pub(super) fn invalid_syntax(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidSyntax(message.into()))
+    }
+
+    pub(super) fn limit_exceeded(limit: &'static str, max: usize) -> Self {
+        Self::new(ErrorKind::LimitExceeded { limit, max })
+    }
+
+    /// Attaches a location within `input` unless the error already has one.
+    pub(super) fn at(mut self, offset: usize, path: &[String], input: &str) -> Self {
+        if self.location.is_none() {
+            let before = &input[..offset];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+            self.location = Some(Box::new(Location {
+                offset,
+                line: before.matches('\n').count() + 1,
+                column: before[line_start..].chars().count() + 1,
+                path: Some(path.join(".")).filter(|path| !path.is_empty()),
+            }));
+        }
+
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if let Some(path) = self.path() {
+            write!(f, " at `{path}`")?;
+        }
+
+        if let Some(location) = &self.location {
+            write!(f, " (line {}, column {})", location.line, location.column)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Io(err) => write!(f, "{err}"),
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ErrorKind::UnexpectedToken { expected, actual } => {
+                write!(f, "expected {expected}, but got {actual:?}")
+            }
+            ErrorKind::InvalidSyntax(message) => write!(f, "{message}"),
+            ErrorKind::TypeMismatch { expected, actual } => {
+                write!(f, "invalid type: {actual}, expected {expected}")
+            }
+            ErrorKind::LimitExceeded { limit, max } => write!(f, "{limit} of {max} exceeded"),
+            ErrorKind::Message(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::new(ErrorKind::Io(err))
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Message(msg.to_string()))
+    }
+
+    fn invalid_type(unexpected: de::Unexpected, expected: &dyn de::Expected) -> Self {
+        Error::new(ErrorKind::TypeMismatch {
+            expected: expected.to_string(),
+            actual: unexpected.to_string(),
+        })
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Message(msg.to_string()))
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt::{self, Display};
+
+use super::error::{Error, ErrorKind, Result};
+
+const TEXT_ENCODING: &str = "text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d}";
+const GENERIC_FORMAT: &str = "generic:version{7412167c-06e9-4698-aff2-e63eb59037e7}";
+
+/// The comment that KV3 documents start with, naming their encoding and format, e.g.
+///
+/// ```text
+/// <!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->
+/// ```
+///
+/// The default is the text encoding with the generic format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// The encoding with its version, e.g. `text:version{...}`.
+    pub encoding: String,
+    /// The format with its version, e.g. `generic:version{...}`,
+    /// or one specific to the kind of document such as a particle system.
+    pub format: String,
+}
+
+impl Header {
+    /// Reads the header at the start of a document, if there is one.
+    pub fn read(input: &str) -> Result<Option<Header>> {
+        Ok(Self::parse(input)?.map(|(header, _)| header))
+    }
+
+    /// Returns the header along with the number of bytes up to its end.
+    pub(super) fn parse(input: &str) -> Result<Option<(Header, usize)>> {
+        let start = input.len() - input.trim_start().len();
+
+        let comment = match input[start..].strip_prefix("<!--") {
+            Some(comment) => comment,
+            None => return Ok(None),
+        };
+
+        let end = match comment.find("-->") {
+            Some(end) => end,
+            None => return Err(Error::new(ErrorKind::UnexpectedEof)),
+        };
+
+        let mut words = comment[..end].split_whitespace();
+        let mut encoding = None;
+        let mut format = None;
+
+        if words.next() != Some("kv3") {
+            return Err(Error::invalid_syntax("expected kv3 header"));
+        }
+
+        for word in words {
+            if let Some(value) = word.strip_prefix("encoding:") {
+                encoding = Some(value.to_string());
+            } else if let Some(value) = word.strip_prefix("format:") {
+                format = Some(value.to_string());
+            }
+        }
+
+        match (encoding, format) {
+            (Some(encoding), Some(format)) => {
+                let header = Header { encoding, format };
+
+                Ok(Some((header, start + "<!--".len() + end + "-->".len())))
+            }
+            _ => Err(Error::invalid_syntax(
+                "kv3 header must name an encoding and a format",
+            )),
+        }
+    }
+
+    pub(super) fn is_text(&self) -> bool {
+        self.encoding.starts_with("text:")
+    }
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            encoding: TEXT_ENCODING.to_string(),
+            format: GENERIC_FORMAT.to_string(),
+        }
+    }
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<!-- kv3 encoding:{} format:{} -->",
+            self.encoding, self.format
+        )
+    }
+}
@@ -0,0 +1,595 @@
+use std::io::Write;
+use std::str;
+
+use serde::ser::{self, Impossible};
+use serde::Serialize;
+
+use super::error::{Error, Result};
+use super::{Flag, Header, TokenSerializer};
+
+/// Serializes a value with the default header, see `TokenSerializer`.
+pub fn to_writer<S: Serialize, W: Write>(writer: W, value: &S) -> Result<()> {
+    to_writer_with_header(writer, value, &Header::default())
+}
+
+/// Serializes a value with the given header,
+/// e.g. one read from the document the value came from.
+pub fn to_writer_with_header<S: Serialize, W: Write>(
+    writer: W,
+    value: &S,
+    header: &Header,
+) -> Result<()> {
+    let mut ser = Serializer {
+        token_serializer: TokenSerializer::new(writer).with_header(header.clone()),
+    };
+
+    value.serialize(&mut ser)
+}
+
+pub fn to_string<S: Serialize>(value: &S) -> Result<String> {
+    let mut bytes = Vec::new();
+
+    to_writer(&mut bytes, value)?;
+
+    // Only ever valid UTF-8 is written
+    Ok(String::from_utf8(bytes).expect("kv3 is valid UTF-8"))
+}
+
+struct Serializer<W> {
+    token_serializer: TokenSerializer<W>,
+}
+
+impl<W> Serializer<W>
+where
+    W: Write,
+{
+    /// Writes `{ variant = ... }` around the contents of a non-unit variant.
+    fn emit_variant_start(&mut self, variant: &'static str) -> Result<()> {
+        self.token_serializer.emit_object_start()?;
+        self.token_serializer.emit_key(variant.to_string())
+    }
+}
+
+impl<'a, W> ser::Serializer for &'a mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray<'a, W>;
+    type SerializeTuple = SerializeArray<'a, W>;
+    type SerializeTupleStruct = SerializeArray<'a, W>;
+    type SerializeTupleVariant = SerializeArray<'a, W>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.token_serializer.emit_bool(value)
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<()> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<()> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<()> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        self.token_serializer.emit_int(value)
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        match (i64::try_from(value), u64::try_from(value)) {
+            (Ok(value), _) => self.serialize_i64(value),
+            (_, Ok(value)) => self.serialize_u64(value),
+            _ => Err(ser::Error::custom("kv3 integers must fit 64 bits")),
+        }
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.token_serializer.emit_uint(value)
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        match u64::try_from(value) {
+            Ok(value) => self.serialize_u64(value),
+            Err(_) => Err(ser::Error::custom("kv3 integers must fit 64 bits")),
+        }
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<()> {
+        // Widened through its shortest representation, so that e.g. 0.1 stays 0.1
+        let value = value.to_string().parse().expect("f32 is a valid f64");
+
+        self.serialize_f64(value)
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<()> {
+        self.token_serializer.emit_float(value)
+    }
+
+    fn serialize_char(self, value: char) -> Result<()> {
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.token_serializer.emit_string(value)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        self.token_serializer.emit_binary(value)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.token_serializer.emit_null()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.token_serializer.emit_null()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.token_serializer.emit_null()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Some(flag) = Flag::from_token(name) {
+            self.token_serializer.emit_flag(flag)?;
+        }
+
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.emit_variant_start(variant)?;
+
+        value.serialize(&mut *self)?;
+
+        self.token_serializer.emit_object_end()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.token_serializer.emit_array_start()?;
+
+        Ok(SerializeArray {
+            ser: self,
+            variant: false,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.emit_variant_start(variant)?;
+        self.token_serializer.emit_array_start()?;
+
+        Ok(SerializeArray {
+            ser: self,
+            variant: true,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.token_serializer.emit_object_start()?;
+
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.token_serializer.emit_object_start()?;
+
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.emit_variant_start(variant)?;
+        self.token_serializer.emit_object_start()?;
+
+        Ok(self)
+    }
+}
+
+impl<W> ser::SerializeMap for &mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = key.serialize(KeySerializer)?;
+
+        self.token_serializer.emit_key(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.token_serializer.emit_object_end()
+    }
+}
+
+impl<W> ser::SerializeStruct for &mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.token_serializer.emit_key(key.to_string())?;
+
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.token_serializer.emit_object_end()
+    }
+}
+
+impl<W> ser::SerializeStructVariant for &mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.token_serializer.emit_object_end()?;
+        self.token_serializer.emit_object_end()
+    }
+}
+
+struct SerializeArray<'a, W> {
+    ser: &'a mut Serializer<W>,
+    // Whether the array is wrapped in an object keyed by the variant
+    variant: bool,
+}
+
+impl<'a, W> ser::SerializeSeq for SerializeArray<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.ser.token_serializer.emit_array_end()?;
+
+        if self.variant {
+            self.ser.token_serializer.emit_object_end()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, W> ser::SerializeTuple for SerializeArray<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, W> ser::SerializeTupleStruct for SerializeArray<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, W> ser::SerializeTupleVariant for SerializeArray<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Turns map keys into strings, as KV3 keys are always strings.
+struct KeySerializer;
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, value: bool) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_char(self, value: char) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<String> {
+        match str::from_utf8(value) {
+            Ok(value) => Ok(value.to_string()),
+            Err(_) => Err(ser::Error::custom("kv3 keys must be valid UTF-8")),
+        }
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(unsupported_key())
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(unsupported_key())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(unsupported_key())
+    }
+}
+
+fn unsupported_key() -> Error {
+    ser::Error::custom("unsupported key type")
+}
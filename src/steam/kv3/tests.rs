@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use super::{from_str, from_value, to_string, to_value, to_writer_with_header};
+use super::{ErrorKind, Flag, Header, Value};
+
+const SMART_PROP: &str = r#"<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->
+{
+	generic_data_type = "CSmartPropRoot"
+	// Comments are skipped
+	m_nVersion = 3
+	m_flScale = 0.5
+	m_bEnabled = true
+	m_Parent = null
+	m_Children =
+	[
+		{
+			_class = "CSmartPropElement_Model"
+			m_sModelName = resource_name:"models/props/crate.vmdl"
+			m_vOrigin = [ 0.0, -16, 1e2 ]
+		},
+		/* and so are
+		   block comments */
+		subclass:
+		{
+			_class = "CSmartPropElement_Group"
+			"display name" = "Group \"A\""
+		},
+	]
+	m_Notes = """
+First line
+Second line
+"""
+	m_Blob = #[ 00 1F ff ]
+}
+"#;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct SmartProp {
+    generic_data_type: String,
+    #[serde(rename = "m_nVersion")]
+    version: u32,
+    #[serde(rename = "m_flScale")]
+    scale: f32,
+    #[serde(rename = "m_bEnabled")]
+    enabled: bool,
+    #[serde(rename = "m_Parent")]
+    parent: Option<String>,
+    #[serde(rename = "m_Children")]
+    children: Vec<Element>,
+    #[serde(rename = "m_Notes")]
+    notes: String,
+    #[serde(rename = "m_Blob", with = "serde_bytes")]
+    blob: Vec<u8>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(tag = "_class")]
+enum Element {
+    #[serde(rename = "CSmartPropElement_Model")]
+    Model {
+        #[serde(rename = "m_sModelName")]
+        model_name: String,
+        #[serde(rename = "m_vOrigin")]
+        origin: [f32; 3],
+    },
+    #[serde(rename = "CSmartPropElement_Group")]
+    Group {
+        #[serde(rename = "display name")]
+        display_name: String,
+    },
+}
+
+/// Reads and writes byte blobs, which serde treats as sequences by default.
+mod serde_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Vec::deserialize(deserializer)
+    }
+}
+
+#[test]
+fn deserialize() {
+    let smart_prop: SmartProp = from_str(SMART_PROP).unwrap();
+
+    assert_eq!(
+        smart_prop,
+        SmartProp {
+            generic_data_type: "CSmartPropRoot".to_string(),
+            version: 3,
+            scale: 0.5,
+            enabled: true,
+            parent: None,
+            children: vec![
+                Element::Model {
+                    model_name: "models/props/crate.vmdl".to_string(),
+                    origin: [0.0, -16.0, 100.0],
+                },
+                Element::Group {
+                    display_name: "Group \"A\"".to_string(),
+                },
+            ],
+            notes: "First line\nSecond line".to_string(),
+            blob: vec![0x00, 0x1f, 0xff],
+        }
+    );
+}
+
+#[test]
+fn serialize() {
+    let smart_prop: SmartProp = from_str(SMART_PROP).unwrap();
+
+    assert_eq!(
+        to_string(&smart_prop).unwrap(),
+        r#"<!-- kv3 encoding:text:version{e21c7f3c-8a33-41c5-9977-a76d3a32aa0d} format:generic:version{7412167c-06e9-4698-aff2-e63eb59037e7} -->
+{
+	generic_data_type = "CSmartPropRoot"
+	m_nVersion = 3
+	m_flScale = 0.5
+	m_bEnabled = true
+	m_Parent = null
+	m_Children =
+	[
+		{
+			_class = "CSmartPropElement_Model"
+			m_sModelName = "models/props/crate.vmdl"
+			m_vOrigin =
+			[
+				0.0,
+				-16.0,
+				100.0,
+			]
+		},
+		{
+			_class = "CSmartPropElement_Group"
+			"display name" = "Group \"A\""
+		},
+	]
+	m_Notes = "First line\nSecond line"
+	m_Blob = #[ 00 1F FF ]
+}
+"#
+    );
+}
+
+#[test]
+fn value_round_trip() {
+    let value: Value = from_str(SMART_PROP).unwrap();
+
+    assert_eq!(value["m_nVersion"], Value::Int(3));
+    assert_eq!(
+        value["m_Children"][1]["_class"],
+        "CSmartPropElement_Group".into()
+    );
+    assert_eq!(
+        value["m_Children"][0]["m_sModelName"],
+        Value::Flagged(
+            Flag::ResourceName,
+            Box::new("models/props/crate.vmdl".into())
+        )
+    );
+    assert_eq!(
+        value["m_Children"][0]["m_sModelName"].as_str(),
+        Some("models/props/crate.vmdl")
+    );
+    assert_eq!(
+        value["m_Children"][1].flags().collect::<Vec<_>>(),
+        [Flag::SubClass]
+    );
+
+    let string = to_string(&value).unwrap();
+
+    assert!(string.contains("\t\tm_sModelName = resource_name:\"models/props/crate.vmdl\"\n"));
+    assert!(string.contains("\t\tsubclass:\n\t\t{\n"));
+    assert_eq!(from_str::<Value>(&string).unwrap(), value);
+}
+
+#[test]
+fn flags() {
+    let value: Value =
+        from_str(r#"{ a = resource+panorama:"file.png" b = [ soundevent:"x" ] }"#).unwrap();
+
+    assert_eq!(
+        value["a"].flags().collect::<Vec<_>>(),
+        [Flag::Resource, Flag::Panorama]
+    );
+    assert_eq!(
+        value["b"][0].flags().collect::<Vec<_>>(),
+        [Flag::SoundEvent]
+    );
+
+    let string = to_string(&value).unwrap();
+    assert!(string.contains("\ta = resource+panorama:\"file.png\"\n"));
+    assert!(string.contains("\t\tsoundevent:\"x\",\n"));
+
+    // Other formats only see the flagged values
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        r#"{"a":"file.png","b":["x"]}"#
+    );
+
+    // Flagged nulls are still nulls, and flagged values keep their flags
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Optional {
+        m: Option<String>,
+        n: Option<Value>,
+    }
+
+    let optional: Optional = from_str("{ m = resource:null n = resource:\"x\" }").unwrap();
+    assert_eq!(optional.m, None);
+    assert_eq!(
+        optional.n.unwrap().flags().collect::<Vec<_>>(),
+        [Flag::Resource]
+    );
+
+    let err = from_str::<Value>(r#"{ a = resource+"x" }"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected another flag after `resource+` at `a` (line 1, column 7)"
+    );
+
+    let err = from_str::<Value>("{ a = unknown:1 }").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown flag `unknown` at `a` (line 1, column 7)"
+    );
+}
+
+#[test]
+fn values() {
+    let value: Value = from_str(
+        "{ max = 18446744073709551615 min = -9223372036854775808 nan = nan neg = -inf empty = [] }",
+    )
+    .unwrap();
+
+    assert_eq!(value["max"], Value::UInt(u64::MAX));
+    assert_eq!(value["min"], Value::Int(i64::MIN));
+    assert!(value["nan"].as_f64().unwrap().is_nan());
+    assert_eq!(value["neg"], Value::Float(f64::NEG_INFINITY));
+    assert_eq!(value["empty"], Value::Array(Vec::new()));
+
+    let mut map = BTreeMap::new();
+    map.insert(1u32, vec![1.5f64, 2.0]);
+
+    let value = to_value(&map).unwrap();
+    assert_eq!(value["1"][1], Value::Float(2.0));
+    assert_eq!(from_value::<BTreeMap<u32, Vec<f64>>>(value).unwrap(), map);
+
+    // The root may be any value
+    assert_eq!(from_str::<Vec<i32>>("[1, 2, 3,]").unwrap(), [1, 2, 3]);
+    assert_eq!(from_str::<String>(r#""root""#).unwrap(), "root");
+}
+
+#[test]
+fn headers() {
+    let header = Header::read(SMART_PROP).unwrap().unwrap();
+    assert_eq!(header, Header::default());
+
+    let header = Header {
+        format: "vpcf1:version{d47d07e6-072c-49cb-9718-5bfd8d6c3d21}".to_string(),
+        ..Header::default()
+    };
+
+    let mut object = IndexMap::new();
+    object.insert(
+        "_class".to_string(),
+        Value::from("CParticleSystemDefinition"),
+    );
+
+    let mut bytes = Vec::new();
+    to_writer_with_header(&mut bytes, &Value::Object(object), &header).unwrap();
+    let string = String::from_utf8(bytes).unwrap();
+
+    assert!(string.starts_with(&format!("{header}\n{{\n")));
+    assert_eq!(Header::read(&string).unwrap(), Some(header));
+
+    // A missing header is allowed, but not a different encoding
+    assert_eq!(Header::read("{}").unwrap(), None);
+    assert_eq!(
+        from_str::<Value>("{}").unwrap(),
+        Value::Object(IndexMap::new())
+    );
+
+    let err =
+        from_str::<Value>("<!-- kv3 encoding:binary:version{0} format:generic:version{0} --> {}")
+            .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unsupported kv3 encoding binary:version{0} (line 1, column 1)"
+    );
+}
+
+#[test]
+fn errors() {
+    let err = from_str::<Value>("{\n\ta = [\n\t\t1\n\t\t2\n\t]\n}").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected `,` or `]` at `a.0` (line 4, column 3)"
+    );
+    assert_eq!(err.line(), Some(4));
+    assert_eq!(err.offset(), Some(15));
+
+    let err = from_str::<Value>("{ a = 1 b 2 }").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected `=` after key at `b` (line 1, column 9)"
+    );
+
+    let err = from_str::<Value>("{ a = \"unterminated }").unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+
+    let err = from_str::<Value>("{ a = #[ 0 ] }").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid binary blob at `a` (line 1, column 7)"
+    );
+
+    let err = from_str::<Value>("{} {}").unwrap_err();
+    assert_eq!(err.to_string(), "expected end of input (line 1, column 4)");
+
+    let err = from_str::<SmartProp>("{ generic_data_type = 1 }").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: integer `1`, expected a string at `generic_data_type` (line 1, column 23)"
+    );
+}
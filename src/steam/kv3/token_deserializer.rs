@@ -0,0 +1,531 @@
+use std::borrow::Cow;
+
+use super::error::{Error, ErrorKind, Result};
+use super::{Flag, Header, MAX_DEPTH};
+
+/// A pull parser for KV3 text, yielding the document as a flat stream of `Token`s.
+///
+/// The header comment is read along with the first token, see `header`.
+/// A document holds a single root value, usually an object.
+/// Flags such as `resource:` are yielded ahead of the value they apply to.
+///
+/// Cloning is cheap and allows looking ahead without consuming any tokens.
+#[derive(Clone)]
+pub struct TokenDeserializer<'de> {
+    input: &'de str,
+    position: usize,
+    token_offset: usize,
+    next_op: NextOp,
+    header: Option<Header>,
+    // The objects and arrays enclosing the next token, with the number of elements read so far
+    stack: Vec<(Container, usize)>,
+    path: Vec<String>,
+}
+
+impl<'de> TokenDeserializer<'de> {
+    pub fn new(input: &'de str) -> Self {
+        Self {
+            input,
+            position: 0,
+            token_offset: 0,
+            next_op: NextOp::Header,
+            header: None,
+            stack: Vec::new(),
+            path: Vec::new(),
+        }
+    }
+
+    /// The header of the document, once the first token has been read.
+    /// Documents without a header are read as if they had the default one.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// The number of objects and arrays enclosing the next token.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// The keys and array indices leading to the most recent token.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// The number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Attaches the location of the most recent token to `err`.
+    pub(super) fn locate(&self, err: Error) -> Error {
+        err.at(self.token_offset, self.path(), self.input)
+    }
+
+    fn handle_header(&mut self) -> Result<Token<'de>> {
+        let header = match Header::parse(self.input)? {
+            Some((header, len)) => {
+                self.position = len;
+                header
+            }
+            None => Header::default(),
+        };
+
+        if !header.is_text() {
+            return Err(Error::invalid_syntax(format!(
+                "unsupported kv3 encoding {}",
+                header.encoding
+            )));
+        }
+
+        self.header = Some(header);
+
+        self.handle_value()
+    }
+
+    fn handle_value(&mut self) -> Result<Token<'de>> {
+        self.skip_whitespace_and_comments()?;
+        self.token_offset = self.position;
+
+        let token = match self.peek() {
+            None => return Err(Error::new(ErrorKind::UnexpectedEof)),
+            Some(b'{') => {
+                self.position += 1;
+                self.push(Container::Object)?;
+                return Ok(Token::ObjectStart);
+            }
+            Some(b'[') => {
+                self.position += 1;
+                self.push(Container::Array)?;
+                return Ok(Token::ArrayStart);
+            }
+            Some(b'"') => Token::String(self.read_string()?),
+            Some(b'#') => Token::Binary(self.read_binary()?),
+            Some(b'0'..=b'9' | b'-' | b'+' | b'.') => self.read_number()?,
+            Some(_) => {
+                let word = self.read_identifier()?;
+
+                // Flags are joined by `+` and end with `:`, e.g. `resource+panorama:`
+                if let Some(b':' | b'+') = self.peek() {
+                    let flag = word.parse()?;
+                    let rest = &self.input[self.position + 1..];
+
+                    if self.peek() == Some(b'+')
+                        && !rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    {
+                        return Err(Error::invalid_syntax(format!(
+                            "expected another flag after `{word}+`"
+                        )));
+                    }
+
+                    self.position += 1;
+                    self.next_op = NextOp::Value;
+                    return Ok(Token::Flag(flag));
+                }
+
+                match word {
+                    "null" => Token::Null,
+                    "true" => Token::Bool(true),
+                    "false" => Token::Bool(false),
+                    // `nan` and `inf`
+                    word => match word.parse() {
+                        Ok(value) => Token::Float(value),
+                        Err(_) => {
+                            return Err(Error::invalid_syntax(format!("unexpected `{word}`")))
+                        }
+                    },
+                }
+            }
+        };
+
+        self.end_of_value();
+
+        Ok(token)
+    }
+
+    fn handle_key_or_object_end(&mut self) -> Result<Token<'de>> {
+        self.skip_whitespace_and_comments()?;
+        self.token_offset = self.position;
+
+        let key = match self.peek() {
+            None => return Err(Error::new(ErrorKind::UnexpectedEof)),
+            Some(b'}') => {
+                self.position += 1;
+                return Ok(self.pop(Token::ObjectEnd));
+            }
+            Some(b'"') => self.read_string()?,
+            Some(_) => Cow::Borrowed(self.read_identifier()?),
+        };
+
+        self.path.truncate(self.depth() - 1);
+        self.path.push(key.to_string());
+
+        self.skip_whitespace_and_comments()?;
+
+        if self.peek() != Some(b'=') {
+            return Err(Error::invalid_syntax("expected `=` after key"));
+        }
+
+        self.position += 1;
+        self.next_op = NextOp::Value;
+
+        Ok(Token::Key(key))
+    }
+
+    fn handle_element_or_array_end(&mut self) -> Result<Token<'de>> {
+        self.skip_whitespace_and_comments()?;
+        self.token_offset = self.position;
+
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(self.pop(Token::ArrayEnd));
+        }
+
+        let depth = self.depth();
+        let index = &mut self.stack[depth - 1].1;
+
+        self.path.truncate(depth - 1);
+        self.path.push(index.to_string());
+        *index += 1;
+
+        self.handle_value()
+    }
+
+    fn handle_comma_or_array_end(&mut self) -> Result<Token<'de>> {
+        self.skip_whitespace_and_comments()?;
+        self.token_offset = self.position;
+
+        match self.peek() {
+            Some(b',') => {
+                // A trailing comma is allowed
+                self.position += 1;
+                self.handle_element_or_array_end()
+            }
+            Some(b']') => {
+                self.position += 1;
+                Ok(self.pop(Token::ArrayEnd))
+            }
+            None => Err(Error::new(ErrorKind::UnexpectedEof)),
+            Some(_) => Err(Error::invalid_syntax("expected `,` or `]`")),
+        }
+    }
+
+    fn handle_end(&mut self) -> Result<()> {
+        self.skip_whitespace_and_comments()?;
+        self.token_offset = self.position;
+
+        if self.position < self.input.len() {
+            return Err(Error::invalid_syntax("expected end of input"));
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, container: Container) -> Result<()> {
+        if self.depth() >= MAX_DEPTH {
+            return Err(Error::limit_exceeded("max_depth", MAX_DEPTH));
+        }
+
+        self.stack.push((container, 0));
+
+        self.next_op = match container {
+            Container::Object => NextOp::KeyOrObjectEnd,
+            Container::Array => NextOp::ElementOrArrayEnd,
+        };
+
+        Ok(())
+    }
+
+    fn pop(&mut self, token: Token<'de>) -> Token<'de> {
+        // Keep the key of the object or array itself
+        self.path.truncate(self.depth() - 1);
+        self.stack.pop();
+
+        self.end_of_value();
+
+        token
+    }
+
+    fn end_of_value(&mut self) {
+        self.next_op = match self.stack.last() {
+            None => NextOp::End,
+            Some((Container::Object, _)) => NextOp::KeyOrObjectEnd,
+            Some((Container::Array, _)) => NextOp::CommaOrArrayEnd,
+        };
+    }
+
+    fn read_identifier(&mut self) -> Result<&'de str> {
+        let rest = &self.input[self.position..];
+        let len = rest
+            .find(|c: char| !is_identifier_char(c))
+            .unwrap_or(rest.len());
+
+        if len == 0 {
+            let c = rest.chars().next().unwrap_or_default();
+            return Err(Error::invalid_syntax(format!("unexpected character {c:?}")));
+        }
+
+        self.position += len;
+
+        Ok(&rest[..len])
+    }
+
+    fn read_number(&mut self) -> Result<Token<'de>> {
+        let rest = &self.input[self.position..];
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && !matches!(c, '+' | '-' | '.'))
+            .unwrap_or(rest.len());
+        let number = &rest[..len];
+
+        self.position += len;
+
+        if let Ok(value) = number.parse() {
+            Ok(Token::Int(value))
+        } else if let Ok(value) = number.parse() {
+            Ok(Token::UInt(value))
+        } else if let Ok(value) = number.parse() {
+            Ok(Token::Float(value))
+        } else {
+            Err(Error::invalid_syntax(format!("invalid number `{number}`")))
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Cow<'de, str>> {
+        if self.input[self.position..].starts_with(r#"""""#) {
+            self.position += 3;
+            return self.read_multiline_string();
+        }
+
+        self.position += 1;
+
+        let rest = &self.input[self.position..];
+
+        let end = match rest.find(['"', '\\']) {
+            Some(end) => end,
+            None => return Err(self.unterminated()),
+        };
+
+        // Strings without escape sequences are borrowed as they are
+        if rest.as_bytes()[end] == b'"' {
+            self.position += end + 1;
+            return Ok(Cow::Borrowed(&rest[..end]));
+        }
+
+        let mut string = String::with_capacity(end);
+        let mut chars = rest.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += i + 1;
+                    return Ok(Cow::Owned(string));
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => unescape(escaped, &mut string),
+                    None => break,
+                },
+                c => string.push(c),
+            }
+        }
+
+        Err(self.unterminated())
+    }
+
+    /// Reads a string between `"""`, which is taken literally
+    /// except for the line breaks after the opening and before the closing quotes.
+    fn read_multiline_string(&mut self) -> Result<Cow<'de, str>> {
+        let rest = &self.input[self.position..];
+        let start = rest.len() - strip_line_break(rest, str::strip_prefix).len();
+
+        let end = match rest[start..].find(r#"""""#) {
+            Some(end) => start + end,
+            None => return Err(self.unterminated()),
+        };
+
+        self.position += end + 3;
+
+        Ok(Cow::Borrowed(strip_line_break(
+            &rest[start..end],
+            str::strip_suffix,
+        )))
+    }
+
+    /// Reads a binary blob, e.g. `#[ 00 ff ]`.
+    fn read_binary(&mut self) -> Result<Vec<u8>> {
+        let rest = match self.input[self.position..].strip_prefix("#[") {
+            Some(rest) => rest,
+            None => return Err(Error::invalid_syntax("expected `#[`")),
+        };
+
+        let end = match rest.find(']') {
+            Some(end) => end,
+            None => return Err(self.unterminated()),
+        };
+
+        self.position += 2 + end + 1;
+
+        let digits = rest[..end]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_digit(16).map(|digit| digit as u8))
+            .collect::<Option<Vec<_>>>();
+        let pairs = digits.as_deref().map(|digits| digits.chunks_exact(2));
+
+        match pairs {
+            Some(pairs) if pairs.remainder().is_empty() => {
+                Ok(pairs.map(|pair| pair[0] << 4 | pair[1]).collect())
+            }
+            _ => Err(Error::invalid_syntax("invalid binary blob")),
+        }
+    }
+
+    fn unterminated(&mut self) -> Error {
+        self.position = self.input.len();
+        Error::new(ErrorKind::UnexpectedEof)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<()> {
+        loop {
+            let rest = &self.input[self.position..];
+            let trimmed = rest.trim_start();
+
+            self.position += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                match comment.find("*/") {
+                    Some(end) => self.position += 2 + end + 2,
+                    None => return Err(self.unterminated()),
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+}
+
+impl<'de> Iterator for TokenDeserializer<'de> {
+    type Item = Result<Token<'de>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match self.next_op {
+            NextOp::Header => self.handle_header(),
+            NextOp::Value => self.handle_value(),
+            NextOp::KeyOrObjectEnd => self.handle_key_or_object_end(),
+            NextOp::ElementOrArrayEnd => self.handle_element_or_array_end(),
+            NextOp::CommaOrArrayEnd => self.handle_comma_or_array_end(),
+            NextOp::End => match self.handle_end() {
+                Ok(()) => {
+                    self.next_op = NextOp::DoNothing;
+                    return None;
+                }
+                Err(err) => Err(err),
+            },
+            NextOp::DoNothing => return None,
+        };
+
+        if let Err(err) = result {
+            self.next_op = NextOp::DoNothing;
+            return Some(Err(self.locate(err)));
+        }
+
+        Some(result)
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.')
+}
+
+/// Whether `key` can be written without quotes.
+pub(super) fn is_identifier(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii() && is_identifier_char(c))
+}
+
+fn strip_line_break<'a>(
+    string: &'a str,
+    strip: fn(&'a str, &'static str) -> Option<&'a str>,
+) -> &'a str {
+    strip(string, "\r\n")
+        .or_else(|| strip(string, "\n"))
+        .unwrap_or(string)
+}
+
+/// Resolves the character following a backslash.
+///
+/// Unknown escape sequences are kept as they are.
+fn unescape(escaped: char, string: &mut String) {
+    match escaped {
+        'n' => string.push('\n'),
+        't' => string.push('\t'),
+        'r' => string.push('\r'),
+        '\\' | '"' => string.push(escaped),
+        _ => {
+            string.push('\\');
+            string.push(escaped);
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum NextOp {
+    DoNothing,
+    Header,
+    Value,
+    KeyOrObjectEnd,
+    ElementOrArrayEnd,
+    CommaOrArrayEnd,
+    End,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum Container {
+    Object,
+    Array,
+}
+
+/// A single piece of KV3 input.
+///
+/// Keys and strings borrow from the input unless they contain escape sequences.
+/// Integers are only read as `UInt` if they do not fit an `Int`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token<'a> {
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
+    Key(Cow<'a, str>),
+    Flag(Flag),
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(Cow<'a, str>),
+    Binary(Vec<u8>),
+}
+
+impl Token<'_> {
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::ObjectStart => Token::ObjectStart,
+            Token::ObjectEnd => Token::ObjectEnd,
+            Token::ArrayStart => Token::ArrayStart,
+            Token::ArrayEnd => Token::ArrayEnd,
+            Token::Key(key) => Token::Key(Cow::Owned(key.into_owned())),
+            Token::Flag(flag) => Token::Flag(flag),
+            Token::Null => Token::Null,
+            Token::Bool(value) => Token::Bool(value),
+            Token::Int(value) => Token::Int(value),
+            Token::UInt(value) => Token::UInt(value),
+            Token::Float(value) => Token::Float(value),
+            Token::String(value) => Token::String(Cow::Owned(value.into_owned())),
+            Token::Binary(value) => Token::Binary(value),
+        }
+    }
+}
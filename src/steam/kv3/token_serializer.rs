@@ -0,0 +1,260 @@
+use std::borrow::Cow;
+use std::io::Write;
+use std::mem;
+
+use serde::ser;
+
+use super::error::Result;
+use super::token_deserializer::{is_identifier, Container};
+use super::{Flag, Header};
+
+/// Writes KV3 text the way Valve's tools do: the header comment first,
+/// then `key = value` entries, with objects and arrays opening on lines
+/// of their own, indented by one tab per level of nesting, and each
+/// array element on a line of its own followed by a comma.
+pub struct TokenSerializer<W> {
+    writer: W,
+    // Taken once it has been written
+    header: Option<Header>,
+    stack: Vec<Container>,
+    current_key: Option<String>,
+    flags: Vec<Flag>,
+    end: bool,
+}
+
+impl<W> TokenSerializer<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            header: Some(Header::default()),
+            stack: Vec::new(),
+            current_key: None,
+            flags: Vec::new(),
+            end: false,
+        }
+    }
+
+    /// The header to write, `Header::default()` unless set.
+    pub fn with_header(mut self, header: Header) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub fn emit_object_start(&mut self) -> Result<()> {
+        self.begin_value(true)?;
+        self.writer.write_all(b"{\n")?;
+        self.stack.push(Container::Object);
+
+        Ok(())
+    }
+
+    pub fn emit_key(&mut self, key: String) -> Result<()> {
+        if self.stack.last() != Some(&Container::Object) || self.current_key.is_some() {
+            return Err(ser::Error::custom("keys must be followed by a value"));
+        }
+
+        if !self.flags.is_empty() {
+            return Err(ser::Error::custom("flags must be followed by a value"));
+        }
+
+        self.current_key = Some(key);
+
+        Ok(())
+    }
+
+    pub fn emit_object_end(&mut self) -> Result<()> {
+        self.end_container(Container::Object, b"}")
+    }
+
+    pub fn emit_array_start(&mut self) -> Result<()> {
+        self.begin_value(true)?;
+        self.writer.write_all(b"[\n")?;
+        self.stack.push(Container::Array);
+
+        Ok(())
+    }
+
+    pub fn emit_array_end(&mut self) -> Result<()> {
+        self.end_container(Container::Array, b"]")
+    }
+
+    /// Applies to the next value, after any flags emitted before.
+    pub fn emit_flag(&mut self, flag: Flag) -> Result<()> {
+        self.flags.push(flag);
+
+        Ok(())
+    }
+
+    pub fn emit_null(&mut self) -> Result<()> {
+        self.emit_scalar("null")
+    }
+
+    pub fn emit_bool(&mut self, value: bool) -> Result<()> {
+        self.emit_scalar(if value { "true" } else { "false" })
+    }
+
+    pub fn emit_int(&mut self, value: i64) -> Result<()> {
+        self.emit_scalar(&value.to_string())
+    }
+
+    pub fn emit_uint(&mut self, value: u64) -> Result<()> {
+        self.emit_scalar(&value.to_string())
+    }
+
+    pub fn emit_float(&mut self, value: f64) -> Result<()> {
+        // Unlike `Display`, `Debug` keeps the fraction of whole numbers, e.g. `1.0`
+        self.emit_scalar(&format!("{value:?}"))
+    }
+
+    pub fn emit_string(&mut self, value: &str) -> Result<()> {
+        self.emit_scalar(&format!("\"{}\"", escape(value)))
+    }
+
+    pub fn emit_binary(&mut self, value: &[u8]) -> Result<()> {
+        if value.is_empty() {
+            return self.emit_scalar("#[]");
+        }
+
+        let bytes = value
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect::<Vec<_>>();
+
+        self.emit_scalar(&format!("#[ {} ]", bytes.join(" ")))
+    }
+
+    fn emit_scalar(&mut self, value: &str) -> Result<()> {
+        self.begin_value(false)?;
+        self.writer.write_all(value.as_bytes())?;
+
+        self.end_of_value()
+    }
+
+    /// Writes the key and flags of the next value.
+    fn begin_value(&mut self, container: bool) -> Result<()> {
+        if self.end {
+            return Err(ser::Error::custom("kv3 documents have a single root value"));
+        }
+
+        // Whether the line holds anything but indentation
+        let mut started = false;
+
+        match self.stack.last() {
+            None => {
+                if let Some(header) = self.header.take() {
+                    writeln!(self.writer, "{header}")?;
+                }
+            }
+            Some(Container::Object) => {
+                let key = match self.current_key.take() {
+                    Some(key) => key,
+                    None => return Err(ser::Error::custom("values within objects need a key")),
+                };
+
+                self.write_indent()?;
+                self.write_key(&key)?;
+                self.writer.write_all(b" =")?;
+                started = true;
+            }
+            Some(Container::Array) => self.write_indent()?,
+        }
+
+        let flags = mem::take(&mut self.flags);
+
+        if started && (!flags.is_empty() || !container) {
+            self.writer.write_all(b" ")?;
+        }
+
+        if !flags.is_empty() {
+            let flags = flags.iter().map(|flag| flag.as_str()).collect::<Vec<_>>();
+
+            write!(self.writer, "{}:", flags.join("+"))?;
+            started = true;
+        }
+
+        if container && started {
+            self.writer.write_all(b"\n")?;
+            self.write_indent()?;
+        }
+
+        Ok(())
+    }
+
+    fn end_container(&mut self, container: Container, close: &[u8]) -> Result<()> {
+        if self.stack.last() != Some(&container) || self.current_key.is_some() {
+            let name = match container {
+                Container::Object => "object",
+                Container::Array => "array",
+            };
+
+            return Err(ser::Error::custom(format!("unexpected end of {name}")));
+        }
+
+        self.stack.pop();
+
+        self.write_indent()?;
+        self.writer.write_all(close)?;
+
+        self.end_of_value()
+    }
+
+    fn end_of_value(&mut self) -> Result<()> {
+        match self.stack.last() {
+            Some(Container::Array) => self.writer.write_all(b",\n")?,
+            Some(Container::Object) => self.writer.write_all(b"\n")?,
+            None => {
+                self.writer.write_all(b"\n")?;
+                self.end = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_indent(&mut self) -> Result<()> {
+        for _ in 0..self.stack.len() {
+            self.writer.write_all(b"\t")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_key(&mut self, key: &str) -> Result<()> {
+        if is_identifier(key) {
+            self.writer.write_all(key.as_bytes())?;
+        } else {
+            write!(self.writer, "\"{}\"", escape(key))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Escapes a string to be written between quotes.
+fn escape(value: &str) -> Cow<'_, str> {
+    if !value.contains(['\\', '"', '\n', '\t', '\r']) {
+        return Cow::Borrowed(value);
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 2);
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
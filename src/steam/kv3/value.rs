@@ -0,0 +1,462 @@
+use std::fmt::{self, Display};
+use std::iter;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+use serde::de::{DeserializeOwned, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize};
+
+use super::error::{Error, Result};
+use super::{from_str, to_string};
+
+// Flags are passed through the KV3 (de)serializer as newtype structs named
+// after `Flag::token`, and asked for by `Value` under this private name,
+// so that they survive a round-trip through `Value` unchanged.
+// Other formats only ever see the flagged value.
+pub(super) const VALUE_TOKEN: &str = "$kv3::Value";
+
+/// Converts a `Value` into any deserializable type.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    let string = to_string(&value)?;
+
+    from_str(&string)
+}
+
+/// Converts any serializable type into a `Value`.
+pub fn to_value<T: Serialize>(value: &T) -> Result<Value> {
+    let string = to_string(value)?;
+
+    from_str(&string)
+}
+
+/// A KV3 document or any value within it.
+///
+/// Integers are only kept as `UInt` if they do not fit an `Int`.
+/// The accessors look through flags, so e.g. `as_str` returns
+/// the path of `resource:"models/a.vmdl"`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<Value>),
+    Object(IndexMap<String, Value>),
+    Flagged(Flag, Box<Value>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// The value without any flags.
+    pub fn unflagged(&self) -> &Value {
+        match self {
+            Value::Flagged(_, value) => value.unflagged(),
+            value => value,
+        }
+    }
+
+    pub fn unflagged_mut(&mut self) -> &mut Value {
+        match self {
+            Value::Flagged(_, value) => value.unflagged_mut(),
+            value => value,
+        }
+    }
+
+    /// The flags of the value, outermost first.
+    pub fn flags(&self) -> impl Iterator<Item = Flag> + '_ {
+        iter::successors(Some(self), |value| match value {
+            Value::Flagged(_, value) => Some(value),
+            _ => None,
+        })
+        .filter_map(|value| match value {
+            Value::Flagged(flag, _) => Some(*flag),
+            _ => None,
+        })
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.unflagged(), Value::Null)
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self.unflagged(), Value::Object(_))
+    }
+
+    pub fn as_object(&self) -> Option<&IndexMap<String, Value>> {
+        match self.unflagged() {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut IndexMap<String, Value>> {
+        match self.unflagged_mut() {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self.unflagged() {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self.unflagged_mut() {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.unflagged() {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self.unflagged() {
+            Value::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self.unflagged() {
+            Value::Int(value) => Some(value),
+            Value::UInt(value) => value.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self.unflagged() {
+            Value::Int(value) => value.try_into().ok(),
+            Value::UInt(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns integers as well, as some tools write whole floats without a fraction.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self.unflagged() {
+            Value::Int(value) => Some(value as f64),
+            Value::UInt(value) => Some(value as f64),
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.unflagged() {
+            Value::Binary(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("no entry found for key {key:?}"),
+        }
+    }
+}
+
+impl IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        match self.get_mut(key) {
+            Some(value) => value,
+            None => panic!("no entry found for key {key:?}"),
+        }
+    }
+}
+
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        match self.as_array().and_then(|array| array.get(index)) {
+            Some(value) => value,
+            None => panic!("no element found at index {index}"),
+        }
+    }
+}
+
+impl IndexMut<usize> for Value {
+    fn index_mut(&mut self, index: usize) -> &mut Value {
+        match self.as_array_mut().and_then(|array| array.get_mut(index)) {
+            Some(value) => value,
+            None => panic!("no element found at index {index}"),
+        }
+    }
+}
+
+impl From<IndexMap<String, Value>> for Value {
+    fn from(object: IndexMap<String, Value>) -> Self {
+        Value::Object(object)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(array: Vec<Value>) -> Self {
+        Value::Array(array)
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Value::String(string)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Value::String(string.to_string())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(value) => serializer.serialize_bool(*value),
+            Value::Int(value) => serializer.serialize_i64(*value),
+            Value::UInt(value) => serializer.serialize_u64(*value),
+            Value::Float(value) => serializer.serialize_f64(*value),
+            Value::String(value) => serializer.serialize_str(value),
+            Value::Binary(value) => serializer.serialize_bytes(value),
+            Value::Array(array) => serializer.collect_seq(array),
+            Value::Object(object) => {
+                let mut map = serializer.serialize_map(Some(object.len()))?;
+
+                for (key, value) in object {
+                    map.serialize_entry(key, value)?;
+                }
+
+                map.end()
+            }
+            Value::Flagged(flag, value) => {
+                serializer.serialize_newtype_struct(flag.token(), value.as_ref())
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a kv3 value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Int(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        match value.try_into() {
+            Ok(value) => Ok(Value::Int(value)),
+            Err(_) => Ok(Value::UInt(value)),
+        }
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Value, E> {
+        Ok(Value::Binary(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Binary(value))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut array = Vec::new();
+
+        while let Some(value) = seq.next_element()? {
+            array.push(value);
+        }
+
+        Ok(Value::Array(array))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut object = IndexMap::new();
+
+        let key = match map.next_key::<String>()? {
+            Some(key) => key,
+            None => return Ok(Value::Object(object)),
+        };
+
+        if let Some(flag) = Flag::from_token(&key) {
+            return Ok(Value::Flagged(flag, Box::new(map.next_value()?)));
+        }
+
+        object.insert(key, map.next_value()?);
+
+        while let Some((key, value)) = map.next_entry()? {
+            object.insert(key, value);
+        }
+
+        Ok(Value::Object(object))
+    }
+}
+
+/// Marks a value as having a special meaning, e.g. `resource:"models/a.vmdl"`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Flag {
+    Resource,
+    ResourceName,
+    Panorama,
+    SoundEvent,
+    SubClass,
+    EntityName,
+}
+
+const FLAGS: [Flag; 6] = [
+    Flag::Resource,
+    Flag::ResourceName,
+    Flag::Panorama,
+    Flag::SoundEvent,
+    Flag::SubClass,
+    Flag::EntityName,
+];
+
+impl Flag {
+    /// The name of the flag as written in KV3 text, e.g. `resource_name`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Flag::Resource => "resource",
+            Flag::ResourceName => "resource_name",
+            Flag::Panorama => "panorama",
+            Flag::SoundEvent => "soundevent",
+            Flag::SubClass => "subclass",
+            Flag::EntityName => "entity_name",
+        }
+    }
+
+    pub(super) fn token(self) -> &'static str {
+        match self {
+            Flag::Resource => "$kv3::Flag::resource",
+            Flag::ResourceName => "$kv3::Flag::resource_name",
+            Flag::Panorama => "$kv3::Flag::panorama",
+            Flag::SoundEvent => "$kv3::Flag::soundevent",
+            Flag::SubClass => "$kv3::Flag::subclass",
+            Flag::EntityName => "$kv3::Flag::entity_name",
+        }
+    }
+
+    pub(super) fn from_token(token: &str) -> Option<Flag> {
+        FLAGS.into_iter().find(|flag| flag.token() == token)
+    }
+}
+
+impl FromStr for Flag {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match FLAGS.into_iter().find(|flag| flag.as_str() == name) {
+            Some(flag) => Ok(flag),
+            None => Err(Error::invalid_syntax(format!("unknown flag `{name}`"))),
+        }
+    }
+}
+
+impl Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -0,0 +1,111 @@
+use std::io::Read;
+
+use anyhow::{bail, Context, Result};
+use serde::de::Deserialize;
+
+use super::binary_vdf::{self, TokenDeserializer};
+use super::read_ext::ReadExt;
+
+const MAGIC_V39: u32 = 0x06565527;
+const MAGIC_V40: u32 = 0x06565528;
+const PACKAGES_END: u32 = 0xFFFFFFFF;
+
+pub fn from_reader<R: Read>(mut reader: R) -> Result<PackageInfoVdf> {
+    let mut bytes = Vec::new();
+
+    reader.read_to_end(&mut bytes)?;
+
+    from_bytes(&bytes)
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<PackageInfoVdf> {
+    let mut reader = bytes;
+
+    let magic = reader.read_u32_le().context("failed to read magic")?;
+    let version = match magic {
+        MAGIC_V39 => 39,
+        MAGIC_V40 => 40,
+        _ => bail!("unknown packageinfo magic: 0x{magic:08x}"),
+    };
+    let universe = reader.read_u32_le()?;
+
+    let mut packages = Vec::new();
+
+    loop {
+        let package_id = reader.read_u32_le()?;
+
+        if package_id == PACKAGES_END {
+            break;
+        }
+
+        let package = read_package(&mut reader, package_id, version)
+            .with_context(|| format!("failed to read package {package_id}"))?;
+
+        packages.push(package);
+    }
+
+    Ok(PackageInfoVdf {
+        version,
+        universe,
+        packages,
+    })
+}
+
+fn read_package(reader: &mut &[u8], package_id: u32, version: u32) -> Result<Package> {
+    let sha1 = reader.read_sha1()?;
+    let change_number = reader.read_u32_le()?;
+    let pics_token = if version >= 40 {
+        Some(reader.read_u64_le()?)
+    } else {
+        None
+    };
+
+    // The KV section carries no size prefix, so its extent is only known
+    // after skipping over it, which neither decodes nor UTF-8 checks it.
+    let mut tokens = TokenDeserializer::from_slice(reader);
+    tokens.skip_value()?;
+
+    let (data, rest) = reader.split_at(tokens.position() as usize);
+    *reader = rest;
+
+    Ok(Package {
+        package_id,
+        sha1,
+        change_number,
+        pics_token,
+        data: data.to_vec(),
+    })
+}
+
+#[derive(Debug)]
+pub struct PackageInfoVdf {
+    pub version: u32,
+    pub universe: u32,
+    pub packages: Vec<Package>,
+}
+
+#[derive(Debug)]
+pub struct Package {
+    pub package_id: u32,
+    pub sha1: [u8; 20],
+    pub change_number: u32,
+    pub pics_token: Option<u64>,
+    data: Vec<u8>,
+}
+
+impl Package {
+    /// The raw binary KV section of this package.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>>(&'de self) -> Result<T> {
+        let value = binary_vdf::from_bytes(&self.data)
+            .with_context(|| format!("failed to deserialize package {}", self.package_id))?;
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests;
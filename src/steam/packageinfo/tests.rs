@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use super::from_bytes;
+
+const PACKAGES: [(u32, &[u32]); 2] = [(0, &[7, 11]), (500, &[10, 20, 30])];
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct PackageData {
+    packageid: u32,
+    appids: Vec<u32>,
+}
+
+/// Builds a packageinfo.vdf holding one package per `(package_id, app_ids)`.
+fn packageinfo(version: u32, packages: &[(u32, &[u32])]) -> Vec<u8> {
+    let magic = match version {
+        39 => 0x06565527u32,
+        40 => 0x06565528,
+        _ => unreachable!(),
+    };
+
+    let mut bytes = Vec::new();
+    bytes.extend(magic.to_le_bytes());
+    bytes.extend(1u32.to_le_bytes());
+
+    for &(package_id, app_ids) in packages {
+        bytes.extend(package_id.to_le_bytes());
+        bytes.extend([package_id as u8; 20]);
+        bytes.extend((package_id + 1000).to_le_bytes());
+
+        if version >= 40 {
+            bytes.extend(0x1122334455u64.to_le_bytes());
+        }
+
+        bytes.extend(package_data(package_id, app_ids));
+    }
+
+    bytes.extend(0xFFFFFFFFu32.to_le_bytes());
+    bytes
+}
+
+/// `"package_id" { packageid = package_id, appids { "0" = app_id, ... } }`
+fn package_data(package_id: u32, app_ids: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    bytes.push(0x00);
+    bytes.extend(package_id.to_string().as_bytes());
+    bytes.push(0x00);
+
+    bytes.push(0x02);
+    bytes.extend(b"packageid\0");
+    bytes.extend(package_id.to_le_bytes());
+
+    bytes.push(0x00);
+    bytes.extend(b"appids\0");
+
+    for (i, app_id) in app_ids.iter().enumerate() {
+        bytes.push(0x02);
+        bytes.extend(i.to_string().as_bytes());
+        bytes.push(0x00);
+        bytes.extend(app_id.to_le_bytes());
+    }
+
+    bytes.extend([0x08, 0x08, 0x08]);
+    bytes
+}
+
+fn assert_packages(version: u32) {
+    let bytes = packageinfo(version, &PACKAGES);
+    let packageinfo = from_bytes(&bytes).unwrap();
+
+    assert_eq!(packageinfo.version, version);
+    assert_eq!(packageinfo.universe, 1);
+    assert_eq!(packageinfo.packages.len(), 2);
+
+    for (package, (package_id, app_ids)) in packageinfo.packages.iter().zip(PACKAGES) {
+        assert_eq!(package.package_id, package_id);
+        assert_eq!(package.sha1, [package_id as u8; 20]);
+        assert_eq!(package.change_number, package_id + 1000);
+
+        if version >= 40 {
+            assert_eq!(package.pics_token, Some(0x1122334455));
+        } else {
+            assert_eq!(package.pics_token, None);
+        }
+
+        // The KV section ends exactly where the next package starts
+        assert_eq!(package.data(), package_data(package_id, app_ids));
+
+        let data = package
+            .deserialize::<BTreeMap<String, PackageData>>()
+            .unwrap();
+
+        assert_eq!(data[&package_id.to_string()].packageid, package_id);
+        assert_eq!(data[&package_id.to_string()].appids, app_ids);
+    }
+}
+
+#[test]
+fn v39() {
+    assert_packages(39);
+}
+
+#[test]
+fn v40() {
+    assert_packages(40);
+}
+
+#[test]
+fn invalid_utf8() {
+    let mut data = vec![0x00];
+    data.extend(b"7\0");
+    data.push(0x01);
+    data.extend(b"name\0\xff\xfe\0");
+    data.extend([0x08, 0x08]);
+
+    let mut bytes = packageinfo(39, &[]);
+    let end = bytes.split_off(8);
+    bytes.extend(7u32.to_le_bytes());
+    bytes.extend([0; 20]);
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(&data);
+    bytes.extend(end);
+
+    // Only deserializing the package checks its strings
+    let packageinfo = from_bytes(&bytes).unwrap();
+    assert_eq!(packageinfo.packages[0].data(), data);
+    assert!(packageinfo.packages[0]
+        .deserialize::<BTreeMap<String, BTreeMap<String, String>>>()
+        .is_err());
+}
+
+#[test]
+fn errors() {
+    let bytes = packageinfo(40, &PACKAGES[..1]);
+
+    // Cut off within the package's KV section
+    let err = from_bytes(&bytes[..bytes.len() - 6]).unwrap_err();
+    assert_eq!(err.to_string(), "failed to read package 0");
+
+    // Cut off before the end marker
+    assert!(from_bytes(&bytes[..bytes.len() - 2]).is_err());
+
+    let err = from_bytes(&[0x29, 0x55, 0x56, 0x06]).unwrap_err();
+    assert_eq!(err.to_string(), "unknown packageinfo magic: 0x06565529");
+}
@@ -0,0 +1,29 @@
+use std::io::{self, Read};
+
+pub trait ReadExt: Read {
+    fn read_u32_le(&mut self) -> io::Result<u32> {
+        let mut buf = [0; 4];
+
+        self.read_exact(&mut buf)?;
+
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64_le(&mut self) -> io::Result<u64> {
+        let mut buf = [0; 8];
+
+        self.read_exact(&mut buf)?;
+
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn read_sha1(&mut self) -> io::Result<[u8; 20]> {
+        let mut buf = [0; 20];
+
+        self.read_exact(&mut buf)?;
+
+        Ok(buf)
+    }
+}
+
+impl<R: Read + ?Sized> ReadExt for R {}
@@ -1,8 +1,30 @@
-use anyhow::Result;
-use serde::Deserialize;
+pub use self::{
+    de::{from_str, from_token_deserializer},
+    document::Document,
+    error::{Error, ErrorKind, Result},
+    loader::Loader,
+    object::Object,
+    platforms::Platforms,
+    ser::{to_string, to_writer},
+    token_deserializer::TokenDeserializer,
+    token_serializer::TokenSerializer,
+    value::{from_value, to_value, Value},
+};
 
-pub fn from_str<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T> {
-    let value = vdf_serde::from_str::<T>(input)?;
+mod de;
+mod document;
+mod error;
+mod loader;
+mod object;
+mod platforms;
+pub mod repeated;
+mod ser;
+pub mod token_deserializer;
+mod token_serializer;
+mod value;
 
-    Ok(value)
-}
+#[cfg(test)]
+mod tests;
+
+/// How deeply objects may be nested, including the root object.
+const MAX_DEPTH: usize = 256;
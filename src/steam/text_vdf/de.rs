@@ -0,0 +1,559 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{
+    self, Deserialize, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+};
+use serde::forward_to_deserialize_any;
+
+use super::error::{Error, ErrorKind, Result};
+use super::repeated::REPEATED_TOKEN;
+use super::token_deserializer::{Token, TokenDeserializer};
+use crate::serde_utils::KeyDeserializer;
+
+/// Deserializes a text vdf document, borrowing from `input` where possible.
+///
+/// The top level entries of the document make up the root object,
+/// so e.g. `libraryfolders.vdf` deserializes into a struct
+/// with a single `libraryfolders` field.
+pub fn from_str<'de, D: Deserialize<'de>>(input: &'de str) -> Result<D> {
+    from_token_deserializer(TokenDeserializer::new(input))
+}
+
+pub fn from_token_deserializer<'de, D: Deserialize<'de>>(
+    token_deserializer: TokenDeserializer<'de>,
+) -> Result<D> {
+    let mut de = Deserializer::new(token_deserializer);
+
+    D::deserialize(&mut de).map_err(|err| de.token_deserializer.locate(err))
+}
+
+struct Deserializer<'de> {
+    token_deserializer: TokenDeserializer<'de>,
+    // Keys within the currently open objects, by depth, whose
+    // occurrences have all been read by `repeated` already
+    collected_keys: Vec<(usize, String)>,
+    // The later occurrences of keys within the currently open objects, by depth,
+    // found by `repeated` reading ahead once per object
+    later_entries: Vec<(usize, HashMap<String, VecDeque<TokenDeserializer<'de>>>)>,
+}
+
+impl<'de> Deserializer<'de> {
+    fn new(token_deserializer: TokenDeserializer<'de>) -> Self {
+        Self {
+            token_deserializer,
+            collected_keys: Vec::new(),
+            later_entries: Vec::new(),
+        }
+    }
+
+    fn expect_token(&mut self) -> Result<Token<'de>> {
+        match self.token_deserializer.next() {
+            Some(token) => token,
+            None => Err(Error::new(ErrorKind::UnexpectedEof)),
+        }
+    }
+
+    fn expect_object_start(&mut self) -> Result<()> {
+        match self.expect_token()? {
+            Token::ObjectStart => Ok(()),
+            token => unexpected_token("object", token),
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<Cow<'de, str>> {
+        match self.expect_token()? {
+            Token::String(value) => Ok(value),
+            token => unexpected_token("string", token),
+        }
+    }
+
+    fn expect_key_or_object_end(&mut self) -> Result<Option<Cow<'de, str>>> {
+        match self.expect_token()? {
+            Token::Key(key) => Ok(Some(key)),
+            Token::ObjectEnd => {
+                let depth = self.token_deserializer.depth();
+
+                self.collected_keys
+                    .retain(|(key_depth, _)| *key_depth <= depth);
+                self.later_entries
+                    .retain(|(key_depth, _)| *key_depth <= depth);
+
+                Ok(None)
+            }
+            token => unexpected_token("key or end of object", token),
+        }
+    }
+
+    fn is_collected(&self, key: &str) -> bool {
+        let depth = self.token_deserializer.depth();
+
+        self.collected_keys
+            .iter()
+            .any(|(key_depth, collected)| *key_depth == depth && collected == key)
+    }
+
+    fn parse<T: FromStr>(&mut self, expected: &'static str) -> Result<T> {
+        let value = self.expect_string()?;
+
+        parse(&value, expected)
+    }
+}
+
+fn unexpected_token<T>(expected: &'static str, token: Token) -> Result<T> {
+    Err(Error::unexpected_token(expected, token.into_owned()))
+}
+
+fn parse<T: FromStr>(value: &str, expected: &'static str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(value), &expected))
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value {
+        "0" | "false" => Ok(false),
+        "1" | "true" => Ok(true),
+        value => Err(de::Error::invalid_value(
+            de::Unexpected::Str(value),
+            &"a bool",
+        )),
+    }
+}
+
+/// Implements `deserialize_*` methods for values that are stored as strings.
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident($expected:literal),)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                visitor.$visit(self.parse($expected)?)
+            }
+        )*
+    };
+}
+
+impl<'de> serde::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_token()? {
+            Token::ObjectStart => visitor.visit_map(self),
+            Token::String(Cow::Borrowed(value)) => visitor.visit_borrowed_str(value),
+            Token::String(Cow::Owned(value)) => visitor.visit_string(value),
+            token => unexpected_token("value", token),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let value = self.expect_string()?;
+
+        visitor.visit_bool(parse_bool(&value)?)
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8("an i8"),
+        deserialize_i16 => visit_i16("an i16"),
+        deserialize_i32 => visit_i32("an i32"),
+        deserialize_i64 => visit_i64("an i64"),
+        deserialize_i128 => visit_i128("an i128"),
+        deserialize_u8 => visit_u8("a u8"),
+        deserialize_u16 => visit_u16("a u16"),
+        deserialize_u32 => visit_u32("a u32"),
+        deserialize_u64 => visit_u64("a u64"),
+        deserialize_u128 => visit_u128("a u128"),
+        deserialize_f32 => visit_f32("an f32"),
+        deserialize_f64 => visit_f64("an f64"),
+        deserialize_char => visit_char("a char"),
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_string()? {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+            Cow::Owned(value) => visitor.visit_string(value),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_string()? {
+            Cow::Borrowed(value) => visitor.visit_borrowed_bytes(value.as_bytes()),
+            Cow::Owned(value) => visitor.visit_byte_buf(value.into_bytes()),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        // Absent values have no representation, so any value that is present is `Some`.
+        // Missing fields are taken care of by serde.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name == REPEATED_TOKEN {
+            return self.deserialize_repeated(visitor);
+        }
+
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.expect_object_start()?;
+
+        let mut seq = Seq {
+            de: &mut *self,
+            index: 0,
+            end: false,
+        };
+        let value = visitor.visit_seq(&mut seq)?;
+
+        // Fixed size sequences like tuples stop reading after their last element
+        if !seq.end {
+            if let Some(key) = self.expect_key_or_object_end()? {
+                return unexpected_token("end of object", Token::Key(key));
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.expect_object_start()?;
+
+        visitor.visit_map(self)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.expect_token()? {
+            Token::String(Cow::Borrowed(variant)) => {
+                visitor.visit_enum(BorrowedStrDeserializer::<Error>::new(variant))
+            }
+            Token::String(Cow::Owned(variant)) => {
+                visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant))
+            }
+            Token::ObjectStart => {
+                let value = visitor.visit_enum(&mut *self)?;
+
+                match self.expect_key_or_object_end()? {
+                    None => Ok(value),
+                    Some(key) => unexpected_token("end of object", Token::Key(key)),
+                }
+            }
+            token => unexpected_token("enum", token),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.token_deserializer.skip_value()?;
+
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        unit unit_struct identifier
+    }
+}
+
+impl<'de> MapAccess<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let key = loop {
+            match self.expect_key_or_object_end()? {
+                // All values of this key have been read together with its first one
+                Some(key) if self.is_collected(&key) => self.token_deserializer.skip_value()?,
+                Some(key) => break key,
+                None => return Ok(None),
+            }
+        };
+
+        let key = seed.deserialize(KeyDeserializer::<Error>::new(key, true))?;
+
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+}
+
+struct Seq<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    // The index the next key is expected to be
+    index: u32,
+    end: bool,
+}
+
+impl<'de, 'a> SeqAccess<'de> for Seq<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let key = match self.de.expect_key_or_object_end()? {
+            Some(key) => key,
+            None => {
+                self.end = true;
+                return Ok(None);
+            }
+        };
+
+        if key.parse::<u32>().ok() != Some(self.index) {
+            return Err(de::Error::custom(format!(
+                "expected sequence index {}, but got key {key:?}",
+                self.index
+            )));
+        }
+
+        self.index += 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    /// Reads the value of the current key, followed by the values
+    /// of all later entries with the same key in the enclosing object.
+    fn deserialize_repeated<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let key = match self.token_deserializer.path().last() {
+            Some(key) => key.clone(),
+            None => return Err(de::Error::custom("repeated values must belong to a key")),
+        };
+        let depth = self.token_deserializer.depth();
+
+        if !self.later_entries.iter().any(|(key_depth, _)| *key_depth == depth) {
+            let entries = self.find_later_entries(&key)?;
+
+            self.later_entries.push((depth, entries));
+        }
+
+        let later = self
+            .later_entries
+            .iter_mut()
+            .find(|(key_depth, _)| *key_depth == depth)
+            .and_then(|(_, entries)| entries.remove(&key))
+            .unwrap_or_default();
+
+        let value = visitor.visit_seq(Repeated {
+            de: &mut *self,
+            first: true,
+            later,
+        })?;
+
+        self.collected_keys.push((depth, key));
+
+        Ok(value)
+    }
+
+    /// Reads ahead to the end of the enclosing object, starting at the value of `key`,
+    /// and returns the entries whose key occurred before, positioned at their values.
+    ///
+    /// The first occurrence of each key is left to be read in order,
+    /// so the object is only read ahead once for all of its repeated keys.
+    fn find_later_entries(
+        &self,
+        key: &str,
+    ) -> Result<HashMap<String, VecDeque<TokenDeserializer<'de>>>> {
+        let mut tokens = self.token_deserializer.clone();
+        let depth = tokens.depth();
+        let mut seen = HashSet::from([key.to_string()]);
+        let mut entries = HashMap::<_, VecDeque<_>>::new();
+
+        tokens.skip_value()?;
+
+        loop {
+            match tokens.next().transpose()? {
+                Some(Token::Key(key)) if tokens.depth() == depth => {
+                    if seen.contains(&*key) {
+                        entries
+                            .entry(key.into_owned())
+                            .or_default()
+                            .push_back(tokens.clone());
+                    } else {
+                        seen.insert(key.into_owned());
+                    }
+
+                    tokens.skip_value()?;
+                }
+                // The enclosing object has ended
+                _ => return Ok(entries),
+            }
+        }
+    }
+}
+
+struct Repeated<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    // Whether the first entry, at which `de` stays, is yet to be read
+    first: bool,
+    // The later entries, positioned at their values
+    later: VecDeque<TokenDeserializer<'de>>,
+}
+
+impl<'de, 'a> SeqAccess<'de> for Repeated<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.first {
+            self.first = false;
+
+            return seed.deserialize(&mut *self.de).map(Some);
+        }
+
+        let mut de = match self.later.pop_front() {
+            Some(tokens) => Deserializer::new(tokens),
+            None => return Ok(None),
+        };
+
+        seed.deserialize(&mut de)
+            .map(Some)
+            .map_err(|err| de.token_deserializer.locate(err))
+    }
+}
+
+impl<'de> EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.next_key_seed(seed)? {
+            Some(variant) => Ok((variant, self)),
+            None => unexpected_token("variant", Token::ObjectEnd),
+        }
+    }
+}
+
+impl<'de> VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(de::Error::custom("expected unit variant to be a string"))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -0,0 +1,495 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::error::{Error, ErrorKind, Result};
+use super::token_deserializer::Lexer;
+use super::token_serializer::escape;
+use super::{Object, Value, MAX_DEPTH};
+
+/// A text vdf document that remembers exactly how it was written.
+///
+/// Comments, whitespace, quoting, conditionals and the order of keys are kept,
+/// so that writing the document back without changes reproduces the input.
+/// Edits only touch the entries they change, and new entries
+/// are indented like their siblings and use the document's line endings.
+///
+/// Paths are lists of keys, starting at the top level of the document.
+/// Like `Object`, lookups only see the first entry of a repeated key,
+/// and conditionals are neither evaluated nor changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Document {
+    root: Block,
+    // The line ending of new entries, taken from the first line of the input
+    newline: &'static str,
+}
+
+impl Document {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            lexer: Lexer::new(input),
+            input,
+            offset: 0,
+            path: Vec::new(),
+        };
+
+        let root = parser.parse_block(true).map_err(|err| {
+            let end = parser.lexer.position().max(parser.offset);
+
+            err.at(parser.offset..end, &parser.path, input)
+        })?;
+
+        let newline = match input.find('\n') {
+            Some(end) if input[..end].ends_with('\r') => "\r\n",
+            _ => "\n",
+        };
+
+        Ok(Self { root, newline })
+    }
+
+    /// The string at `path`, if there is one.
+    pub fn get(&self, path: &[&str]) -> Option<&str> {
+        let (key, parents) = path.split_last()?;
+        let mut block = &self.root;
+
+        for parent in parents {
+            match &block.entry(parent)?.value {
+                Node::Object(child) => block = child,
+                Node::String(_) => return None,
+            }
+        }
+
+        match &block.entry(key)?.value {
+            Node::String(text) => Some(&text.value),
+            Node::Object(_) => None,
+        }
+    }
+
+    /// Sets the value at `path`, creating any missing objects along the way.
+    ///
+    /// An existing entry keeps its position and comments, and a string
+    /// replacing a string keeps its quoting where possible.
+    pub fn set(&mut self, path: &[&str], value: impl Into<Value>) -> Result<()> {
+        let (key, parents) = split_path(path)?;
+        let newline = self.newline;
+        let (block, indent) = self.block_mut(parents, true)?;
+        let value = value.into();
+
+        match block.position(key) {
+            Some(index) => block.entries[index].set(&value, &indent, newline),
+            None => block.append(key, &value, &indent, newline),
+        }
+
+        Ok(())
+    }
+
+    /// Adds an entry at the end of the object at the parent of `path`,
+    /// even if the key is already present, creating any missing objects along the way.
+    pub fn insert(&mut self, path: &[&str], value: impl Into<Value>) -> Result<()> {
+        let (key, parents) = split_path(path)?;
+        let newline = self.newline;
+        let (block, indent) = self.block_mut(parents, true)?;
+
+        block.append(key, &value.into(), &indent, newline);
+
+        Ok(())
+    }
+
+    /// Removes the entry at `path`, along with the comments preceding it.
+    pub fn remove(&mut self, path: &[&str]) -> Option<Value> {
+        let (key, parents) = path.split_last()?;
+        let (block, _) = self.block_mut(parents, false).ok()?;
+        let index = block.position(key)?;
+
+        Some(block.entries.remove(index).value.to_value())
+    }
+
+    /// The document without its formatting, including all entries regardless of conditionals.
+    pub fn to_value(&self) -> Value {
+        Value::Object(self.root.to_object())
+    }
+
+    /// Finds the object at `path` and the indentation of new entries within it.
+    fn block_mut(&mut self, path: &[&str], create: bool) -> Result<(&mut Block, String)> {
+        let newline = self.newline;
+        let mut block = &mut self.root;
+        let mut indent = block.indent().unwrap_or_default();
+
+        for (i, key) in path.iter().enumerate() {
+            let index = match block.position(key) {
+                Some(index) => index,
+                None if create => {
+                    block.append(key, &Value::Object(Object::new()), &indent, newline);
+                    block.entries.len() - 1
+                }
+                None => return Err(not_found(&path[..=i])),
+            };
+
+            let entry = &mut block.entries[index];
+            let entry_indent = entry.indent().unwrap_or(indent);
+
+            block = match &mut entry.value {
+                Node::Object(child) => child,
+                Node::String(_) => return Err(not_an_object(&path[..=i])),
+            };
+            indent = block.indent().unwrap_or(format!("{entry_indent}\t"));
+        }
+
+        Ok((block, indent))
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Self::parse(input)
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.fmt_entries(f)
+    }
+}
+
+fn split_path<'a>(path: &'a [&'a str]) -> Result<(&'a str, &'a [&'a str])> {
+    match path.split_last() {
+        Some((key, parents)) => Ok((key, parents)),
+        None => Err(Error::new(ErrorKind::Message(
+            "path must not be empty".to_string(),
+        ))),
+    }
+}
+
+fn not_found(path: &[&str]) -> Error {
+    Error::new(ErrorKind::Message(format!(
+        "no entry found at `{}`",
+        path.join(".")
+    )))
+}
+
+fn not_an_object(path: &[&str]) -> Error {
+    Error::new(ErrorKind::Message(format!(
+        "`{}` is not an object",
+        path.join(".")
+    )))
+}
+
+/// The entries of an object, or of the document itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Block {
+    entries: Vec<Entry>,
+    // Whitespace and comments after the last entry
+    closing: String,
+}
+
+impl Block {
+    fn position(&self, key: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.key.value == key)
+    }
+
+    fn entry(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.key.value == key)
+    }
+
+    /// The indentation of the entries, if any of them is on a line of its own.
+    fn indent(&self) -> Option<String> {
+        self.entries.iter().rev().find_map(Entry::indent)
+    }
+
+    fn append(&mut self, key: &str, value: &Value, indent: &str, newline: &str) {
+        let prefix = match self.entries.last() {
+            // The first entry of a document is not preceded by a line break
+            None if indent.is_empty() && self.closing.is_empty() => String::new(),
+            // Siblings on a single line, e.g. `{ "a" "1" "b" "2" }`
+            Some(last)
+                if !last.prefix.is_empty()
+                    && last.prefix.trim().is_empty()
+                    && !last.prefix.contains('\n') =>
+            {
+                last.prefix.clone()
+            }
+            _ => format!("{newline}{indent}"),
+        };
+
+        // Keep the closing brace on a line of its own
+        if !indent.is_empty() && !self.closing.contains('\n') && prefix.contains('\n') {
+            self.closing = format!("{newline}{}", indent.strip_suffix('\t').unwrap_or(""));
+        }
+
+        let separator = match value {
+            Value::String(_) => self
+                .entries
+                .iter()
+                .rev()
+                .filter(|entry| matches!(entry.value, Node::String(_)))
+                .map(|entry| &entry.separator)
+                .find(|separator| !separator.is_empty() && separator.trim().is_empty())
+                .cloned()
+                .unwrap_or_else(|| "\t\t".to_string()),
+            Value::Object(_) => format!("{newline}{indent}"),
+        };
+
+        self.entries.push(Entry {
+            prefix,
+            key: Text::quoted(key),
+            separator,
+            value: Node::new(value, indent, newline),
+            suffix: String::new(),
+        });
+    }
+
+    fn to_object(&self) -> Object {
+        self.entries
+            .iter()
+            .map(|entry| (entry.key.value.clone(), entry.value.to_value()))
+            .collect()
+    }
+
+    fn fmt_entries(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            write!(f, "{}{}{}", entry.prefix, entry.key.raw, entry.separator)?;
+
+            match &entry.value {
+                Node::String(text) => write!(f, "{}", text.raw)?,
+                Node::Object(block) => {
+                    write!(f, "{{")?;
+                    block.fmt_entries(f)?;
+                    write!(f, "}}")?;
+                }
+            }
+
+            write!(f, "{}", entry.suffix)?;
+        }
+
+        write!(f, "{}", self.closing)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    // Whitespace and comments before the key
+    prefix: String,
+    key: Text,
+    // Everything between the key and the value, including a conditional
+    separator: String,
+    value: Node,
+    // A conditional and a comment on the same line after the value,
+    // including the whitespace before them
+    suffix: String,
+}
+
+impl Entry {
+    fn indent(&self) -> Option<String> {
+        let (_, indent) = self.prefix.rsplit_once('\n')?;
+
+        Some(indent.to_string())
+    }
+
+    fn set(&mut self, value: &Value, indent: &str, newline: &str) {
+        let indent = self.indent().unwrap_or_else(|| indent.to_string());
+
+        match (&mut self.value, value) {
+            (Node::String(text), Value::String(value)) => *text = text.with_value(value),
+            (Node::Object(_), Value::Object(_)) => self.value = Node::new(value, &indent, newline),
+            (_, Value::String(_)) => {
+                self.separator = "\t\t".to_string();
+                self.value = Node::new(value, &indent, newline);
+            }
+            (_, Value::Object(_)) => {
+                self.separator = format!("{newline}{indent}");
+                self.value = Node::new(value, &indent, newline);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    String(Text),
+    Object(Block),
+}
+
+impl Node {
+    /// Formats a new value the way Steam does, for an entry indented by `indent`.
+    fn new(value: &Value, indent: &str, newline: &str) -> Self {
+        let object = match value {
+            Value::String(value) => return Node::String(Text::quoted(value)),
+            Value::Object(object) => object,
+        };
+
+        let mut block = Block {
+            entries: Vec::new(),
+            closing: format!("{newline}{indent}"),
+        };
+
+        for (key, value) in object {
+            block.append(key, value, &format!("{indent}\t"), newline);
+        }
+
+        Node::Object(block)
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Node::String(text) => Value::String(text.value.clone()),
+            Node::Object(block) => Value::Object(block.to_object()),
+        }
+    }
+}
+
+/// A key or string value, as written and as read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Text {
+    raw: String,
+    value: String,
+}
+
+impl Text {
+    fn quoted(value: &str) -> Self {
+        Self {
+            raw: format!("\"{}\"", escape(value)),
+            value: value.to_string(),
+        }
+    }
+
+    /// A new value written like this one, unquoted if possible.
+    fn with_value(&self, value: &str) -> Self {
+        let can_be_unquoted = !value.is_empty()
+            && !value.starts_with("//")
+            && !value.starts_with('[')
+            && !value.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '{' | '}'));
+
+        if self.raw.starts_with('"') || !can_be_unquoted {
+            return Self::quoted(value);
+        }
+
+        Self {
+            raw: value.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    input: &'a str,
+    // The offset of the most recent token, for errors
+    offset: usize,
+    path: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_block(&mut self, root: bool) -> Result<Block> {
+        let mut entries = Vec::new();
+        let mut prefix_start = self.lexer.position();
+
+        loop {
+            self.lexer.skip_whitespace_and_comments();
+            self.offset = self.lexer.position();
+
+            match self.lexer.peek() {
+                None if root => break,
+                Some(b'}') if !root => break,
+                None => return Err(Error::new(ErrorKind::UnexpectedEof)),
+                Some(b'{' | b'}') => {
+                    let token = self.lexer.read_token()?;
+                    return Err(Error::unexpected_token("key", token.into_owned()));
+                }
+                Some(_) => {}
+            }
+
+            let prefix = self.since(prefix_start);
+            let key = self.parse_text()?;
+
+            self.path.push(key.value.clone());
+
+            let separator_start = self.lexer.position();
+            self.lexer.skip_whitespace_and_comments();
+            if self.lexer.read_condition()?.is_some() {
+                self.lexer.skip_whitespace_and_comments();
+            }
+            let separator = self.since(separator_start);
+
+            self.offset = self.lexer.position();
+
+            let value = match self.lexer.peek() {
+                Some(b'{') => {
+                    if self.path.len() >= MAX_DEPTH {
+                        return Err(Error::limit_exceeded("max_depth", MAX_DEPTH));
+                    }
+
+                    self.lexer.read_token()?;
+                    let block = self.parse_block(false)?;
+                    self.lexer.read_token()?;
+
+                    Node::Object(block)
+                }
+                Some(b'}') | None => {
+                    let token = self.lexer.read_token()?;
+                    return Err(Error::unexpected_token("value", token.into_owned()));
+                }
+                Some(_) => Node::String(self.parse_text()?),
+            };
+
+            // Whitespace is part of the suffix only if a conditional
+            // or a comment on the same line follows
+            let suffix_start = self.lexer.position();
+            self.lexer.skip_whitespace_and_comments();
+            self.offset = self.lexer.position();
+            let mut suffix_end = match self.lexer.read_condition()? {
+                Some(_) => self.lexer.position(),
+                None => suffix_start,
+            };
+            if let Some(end) = self.comment_end(suffix_end) {
+                suffix_end = end;
+            }
+            let suffix = self.input[suffix_start..suffix_end].to_string();
+            prefix_start = suffix_end;
+
+            self.path.pop();
+
+            entries.push(Entry {
+                prefix,
+                key,
+                separator,
+                value,
+                suffix,
+            });
+        }
+
+        Ok(Block {
+            entries,
+            closing: self.since(prefix_start),
+        })
+    }
+
+    fn parse_text(&mut self) -> Result<Text> {
+        let start = self.lexer.position();
+        let value = self.lexer.read_string()?.into_owned();
+
+        Ok(Text {
+            raw: self.since(start),
+            value,
+        })
+    }
+
+    /// The end of a comment on the line that `start` is on, if it is followed by one.
+    fn comment_end(&self, start: usize) -> Option<usize> {
+        let rest = &self.input[start..];
+        let comment = rest.trim_start_matches([' ', '\t']);
+
+        if !comment.starts_with("//") {
+            return None;
+        }
+
+        let line = comment.split('\n').next().unwrap_or_default();
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        Some(start + rest.len() - comment.len() + line.len())
+    }
+
+    fn since(&self, start: usize) -> String {
+        self.input[start..self.lexer.position()].to_string()
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt::{self, Display};
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::{de, ser};
+
+use super::token_deserializer::Token;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    // Boxed to keep results small
+    location: Option<Box<Location>>,
+    file: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+struct Location {
+    span: Range<usize>,
+    // 1-based, at the start of the span
+    line: usize,
+    column: usize,
+    path: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Io(io::Error),
+    UnexpectedEof,
+    UnexpectedToken {
+        expected: &'static str,
+        actual: Token<'static>,
+    },
+    TypeMismatch {
+        expected: String,
+        actual: String,
+    },
+    LimitExceeded {
+        limit: &'static str,
+        max: usize,
+    },
+    /// A file includes itself, directly or through other files.
+    IncludeCycle(PathBuf),
+    Message(String),
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The byte offset of the token in the input at which the error occurred.
+    pub fn offset(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.span.start)
+    }
+
+    /// The byte range of the input from the start of the offending token
+    /// up to where the error was noticed, e.g. the end of an unterminated string.
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.location.as_ref().map(|location| location.span.clone())
+    }
+
+    /// The line of the offending token, starting at 1.
+    pub fn line(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.line)
+    }
+
+    /// The column of the offending token in characters, starting at 1.
+    pub fn column(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.column)
+    }
+
+    /// The dotted path of keys leading to the value at which the error occurred,
+    /// e.g. `libraryfolders.0.path`.
+    pub fn path(&self) -> Option<&str> {
+        self.location.as_ref()?.path.as_deref()
+    }
+
+    /// The file in which the error occurred, when reading files with a `Loader`.
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub(super) fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            location: None,
+            file: None,
+        }
+    }
+
+    pub(super) fn unexpected_token(expected: &'static str, actual: Token<'static>) -> Self {
+        Self::new(ErrorKind::UnexpectedToken { expected, actual })
+    }
+
+    pub(super) fn limit_exceeded(limit: &'static str, max: usize) -> Self {
+        Self::new(ErrorKind::LimitExceeded { limit, max })
+    }
+
+    /// Attaches a location within `input` unless the error already has one.
+    pub(super) fn at(mut self, span: Range<usize>, path: &[String], input: &str) -> Self {
+        if self.location.is_none() {
+            let before = &input[..span.start];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+            self.location = Some(Box::new(Location {
+                line: before.matches('\n').count() + 1,
+                column: before[line_start..].chars().count() + 1,
+                span,
+                path: Some(path.join(".")).filter(|path| !path.is_empty()),
+            }));
+        }
+
+        self
+    }
+
+    /// Formats the error along with the line of `input` it occurred in,
+    /// marking the offending token, e.g.
+    ///
+    /// ```text
+    /// error: expected key, but got ObjectStart at `a.b`
+    ///  --> line 2, column 11
+    ///   |
+    /// 2 |     "b" { {
+    ///   |           ^
+    /// ```
+    ///
+    /// `input` must be the text the error was returned for.
+    pub fn render(&self, input: &str) -> String {
+        let mut rendered = format!("error: {}", self.kind);
+
+        if let Some(path) = self.path() {
+            rendered += &format!(" at `{path}`");
+        }
+
+        let Location {
+            span, line, column, ..
+        } = match self.location.as_deref() {
+            Some(location) => location,
+            None => return rendered,
+        };
+
+        match &self.file {
+            Some(file) => rendered += &format!("\n --> {}:{}:{}", file.display(), line, column),
+            None => rendered += &format!("\n --> line {line}, column {column}"),
+        }
+
+        let line_start = input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[span.start..]
+            .find('\n')
+            .map_or(input.len(), |i| span.start + i);
+        let text = input[line_start..line_end].trim_end_matches('\r');
+
+        // Tabs are kept, so that the marker lines up however wide they are displayed
+        let indent = input[line_start..span.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        // The span may start past the text, e.g. at the end of input after a bare `\r`
+        let end = span.end.min(line_start + text.len()).max(span.start);
+        let width = input[span.start..end].chars().count().max(1);
+
+        let number = line.to_string();
+        let margin = " ".repeat(number.len());
+
+        rendered += &format!(
+            "\n{margin} |\n{number} | {text}\n{margin} | {indent}{}",
+            "^".repeat(width)
+        );
+
+        rendered
+    }
+
+    /// Attaches the file being read unless the error already has one,
+    /// which is then the file included by it.
+    pub(super) fn in_file(mut self, file: &Path) -> Self {
+        if self.file.is_none() {
+            self.file = Some(file.to_owned());
+        }
+
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if let Some(path) = self.path() {
+            write!(f, " at `{path}`")?;
+        }
+
+        if let Some(file) = &self.file {
+            write!(f, " in {}", file.display())?;
+        }
+
+        if let Some(location) = &self.location {
+            write!(f, " (line {}, column {})", location.line, location.column)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Io(err) => write!(f, "{err}"),
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ErrorKind::UnexpectedToken { expected, actual } => {
+                write!(f, "expected {expected}, but got {actual:?}")
+            }
+            ErrorKind::TypeMismatch { expected, actual } => {
+                write!(f, "invalid type: {actual}, expected {expected}")
+            }
+            ErrorKind::LimitExceeded { limit, max } => write!(f, "{limit} of {max} exceeded"),
+            ErrorKind::IncludeCycle(file) => write!(f, "{} includes itself", file.display()),
+            ErrorKind::Message(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::new(ErrorKind::Io(err))
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Message(msg.to_string()))
+    }
+
+    fn invalid_type(unexpected: de::Unexpected, expected: &dyn de::Expected) -> Self {
+        Error::new(ErrorKind::TypeMismatch {
+            expected: expected.to_string(),
+            actual: unexpected.to_string(),
+        })
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Message(msg.to_string()))
+    }
+}
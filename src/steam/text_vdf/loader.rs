@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use super::error::{Error, ErrorKind, Result};
+use super::{from_token_deserializer, from_value, Object, Platforms, TokenDeserializer, Value};
+
+/// Reads text vdf files the way the Source engine and the Steam UI do,
+/// resolving directives and evaluating conditionals.
+///
+/// `#include "file"` appends the top level entries of `file` to the document,
+/// while `#base "file"` only adds those entries that the document lacks,
+/// merging objects present in both.
+/// Paths are relative to the directory of the file containing the directive.
+#[derive(Clone, Debug, Default)]
+pub struct Loader {
+    platforms: Platforms,
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The platforms to evaluate conditionals against, `Platforms::current()` by default.
+    pub fn platforms(mut self, platforms: Platforms) -> Self {
+        self.platforms = platforms;
+        self
+    }
+
+    /// A token deserializer for a single document, leaving directives as they are.
+    pub fn token_deserializer<'de>(&self, input: &'de str) -> TokenDeserializer<'de> {
+        TokenDeserializer::new(input).with_platforms(self.platforms.clone())
+    }
+
+    pub fn load<D: DeserializeOwned>(&self, path: impl AsRef<Path>) -> Result<D> {
+        let object = self.load_file(path.as_ref(), &mut Vec::new())?;
+
+        from_value(Value::Object(object))
+    }
+
+    /// Reads a document that is not backed by a file,
+    /// resolving the paths of its directives relative to `base_path`.
+    pub fn load_str<D: DeserializeOwned>(
+        &self,
+        input: &str,
+        base_path: impl AsRef<Path>,
+    ) -> Result<D> {
+        let object = self.load_document(input, base_path.as_ref(), &mut Vec::new())?;
+
+        from_value(Value::Object(object))
+    }
+
+    /// `includes` holds the files currently being read, to detect cycles.
+    fn load_file(&self, path: &Path, includes: &mut Vec<PathBuf>) -> Result<Object> {
+        let result = fs::canonicalize(path)
+            .map_err(Error::from)
+            .and_then(|canonical| {
+                if includes.contains(&canonical) {
+                    return Err(Error::new(ErrorKind::IncludeCycle(path.to_owned())));
+                }
+
+                let input = fs::read_to_string(path)?;
+                let base_path = path.parent().unwrap_or(Path::new(""));
+
+                includes.push(canonical);
+                let object = self.load_document(&input, base_path, includes);
+                includes.pop();
+
+                object
+            });
+
+        result.map_err(|err| err.in_file(path))
+    }
+
+    fn load_document(
+        &self,
+        input: &str,
+        base_path: &Path,
+        includes: &mut Vec<PathBuf>,
+    ) -> Result<Object> {
+        let (directives, entries): (Vec<_>, Vec<_>) =
+            from_token_deserializer::<Object>(self.token_deserializer(input))?
+                .into_iter()
+                .partition(|(key, _)| directive(key).is_some());
+
+        let mut object = Object::from_iter(entries);
+
+        for (key, value) in directives {
+            let file = match value {
+                Value::String(file) => resolve(base_path, &file),
+                Value::Object(_) => {
+                    return Err(Error::new(ErrorKind::Message(format!(
+                        "{key} must be followed by a file name"
+                    ))))
+                }
+            };
+
+            let included = self.load_file(&file, includes)?;
+
+            match directive(&key) {
+                Some(Directive::Include) => object.extend(included),
+                Some(Directive::Base) => merge_base(&mut object, included),
+                None => unreachable!("only directives are kept"),
+            }
+        }
+
+        Ok(object)
+    }
+}
+
+enum Directive {
+    Include,
+    Base,
+}
+
+fn directive(key: &str) -> Option<Directive> {
+    if key.eq_ignore_ascii_case("#include") {
+        Some(Directive::Include)
+    } else if key.eq_ignore_ascii_case("#base") {
+        Some(Directive::Base)
+    } else {
+        None
+    }
+}
+
+/// Files written on Windows may separate directories with backslashes.
+fn resolve(base_path: &Path, file: &str) -> PathBuf {
+    if cfg!(windows) {
+        base_path.join(file)
+    } else {
+        base_path.join(file.replace('\\', "/"))
+    }
+}
+
+/// Adds the entries of `base` that `object` lacks, recursing into objects present in both.
+fn merge_base(object: &mut Object, base: Object) {
+    for (key, base_value) in base {
+        match (object.get_mut(&key), base_value) {
+            (Some(Value::Object(object)), Value::Object(base)) => merge_base(object, base),
+            (Some(_), _) => {}
+            (None, base_value) => object.append(key, base_value),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::{slice, vec};
+
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize};
+
+use super::Value;
+
+/// The entries of a text vdf object, in document order.
+///
+/// Unlike a map, an object may contain the same key more than once,
+/// e.g. when the Steam client merges several blocks of the same name.
+/// Lookups by key only see the first entry of that key,
+/// use `get_all` to see the others.
+///
+/// Entries are kept in a plain list, so lookups are linear in the size of the object.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Object {
+    entries: Vec<(String, Value)>,
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The value of the first entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The value of the first entry with the given key.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The values of all entries with the given key, in document order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Value> + 'a {
+        self.iter()
+            .filter(move |(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The values of all entries with the given key, in document order.
+    pub fn get_all_mut<'a>(&'a mut self, key: &'a str) -> impl Iterator<Item = &'a mut Value> + 'a {
+        self.iter_mut()
+            .filter(move |(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets the value of `key`, like a map would.
+    ///
+    /// The first entry with that key keeps its position and gets the new value,
+    /// any further entries with that key are removed.
+    /// Returns the previous value of the first entry, if any.
+    pub fn insert(&mut self, key: impl Into<String>, value: Value) -> Option<Value> {
+        let key = key.into();
+
+        let position = match self.entries.iter().position(|(k, _)| *k == key) {
+            Some(position) => position,
+            None => {
+                self.entries.push((key, value));
+                return None;
+            }
+        };
+
+        let previous = std::mem::replace(&mut self.entries[position].1, value);
+
+        let mut index = 0;
+        self.entries.retain(|(k, _)| {
+            let keep = index <= position || *k != key;
+            index += 1;
+            keep
+        });
+
+        Some(previous)
+    }
+
+    /// Adds an entry at the end, regardless of whether the key is already present.
+    pub fn append(&mut self, key: impl Into<String>, value: Value) {
+        self.entries.push((key.into(), value));
+    }
+
+    /// Removes all entries with the given key and returns their values in document order.
+    pub fn remove_all(&mut self, key: &str) -> Vec<Value> {
+        let (removed, kept) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(k, _)| k == key);
+
+        self.entries = kept;
+
+        removed.into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// The keys of all entries, including repeated ones.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            entries: self.entries.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            entries: self.entries.iter_mut(),
+        }
+    }
+}
+
+/// Writes repeated keys as they are, so formats that reject them will fail.
+impl Serialize for Object {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+
+        for (key, value) in self {
+            map.serialize_entry(key, value)?;
+        }
+
+        map.end()
+    }
+}
+
+/// Keeps repeated keys, so that they are written back as they were.
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ObjectVisitor)
+    }
+}
+
+pub(super) struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = Object;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a text vdf object")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Object, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut object = Object::new();
+
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            object.append(key, value);
+        }
+
+        Ok(object)
+    }
+}
+
+impl Index<&str> for Object {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("no entry found for key {key:?}"),
+        }
+    }
+}
+
+impl IndexMut<&str> for Object {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        match self.get_mut(key) {
+            Some(value) => value,
+            None => panic!("no entry found for key {key:?}"),
+        }
+    }
+}
+
+/// Collects entries as they are, keeping repeated keys.
+impl<K: Into<String>> FromIterator<(K, Value)> for Object {
+    fn from_iter<I: IntoIterator<Item = (K, Value)>>(iter: I) -> Self {
+        let mut object = Object::new();
+
+        object.extend(iter);
+
+        object
+    }
+}
+
+/// Appends entries as they are, keeping repeated keys.
+impl<K: Into<String>> Extend<(K, Value)> for Object {
+    fn extend<I: IntoIterator<Item = (K, Value)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.append(key, value);
+        }
+    }
+}
+
+impl IntoIterator for Object {
+    type Item = (String, Value);
+    type IntoIter = vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Object {
+    type Item = (&'a str, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Object {
+    type Item = (&'a str, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+pub struct Iter<'a> {
+    entries: slice::Iter<'a, (String, Value)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .next()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+pub struct IterMut<'a> {
+    entries: slice::IterMut<'a, (String, Value)>,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a str, &'a mut Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .next()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
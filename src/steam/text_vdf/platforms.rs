@@ -0,0 +1,92 @@
+use super::error::{Error, ErrorKind, Result};
+
+/// The set of platforms that conditionals such as `[$WIN32]` are evaluated against.
+///
+/// Names are given without the leading `$` and compared case-insensitively.
+/// Valve uses e.g. `WIN32`, `WINDOWS`, `POSIX`, `LINUX`, `OSX`, `X360` and `DECK`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Platforms {
+    names: Vec<String>,
+}
+
+impl Platforms {
+    /// No platforms at all, so only negated conditionals hold.
+    pub fn none() -> Self {
+        Self { names: Vec::new() }
+    }
+
+    /// The platform this program runs on, e.g. `WIN32` and `WINDOWS` on Windows.
+    pub fn current() -> Self {
+        let names: &[&str] = if cfg!(windows) {
+            &["WIN32", "WINDOWS"]
+        } else if cfg!(target_os = "macos") {
+            &["OSX", "POSIX"]
+        } else if cfg!(target_os = "linux") {
+            &["LINUX", "POSIX"]
+        } else if cfg!(unix) {
+            &["POSIX"]
+        } else {
+            &[]
+        };
+
+        names
+            .iter()
+            .fold(Self::none(), |platforms, name| platforms.with(name))
+    }
+
+    pub fn with(mut self, name: &str) -> Self {
+        let name = name.trim_start_matches('$');
+
+        if !self.contains(name) {
+            self.names.push(name.to_ascii_uppercase());
+        }
+
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        let name = name.trim_start_matches('$');
+
+        self.names.iter().any(|n| n.eq_ignore_ascii_case(name))
+    }
+
+    /// Evaluates the contents of a conditional, e.g. `$WIN32 || !$X360`.
+    ///
+    /// `&&` binds tighter than `||`, and each platform may be negated with `!`.
+    pub(super) fn evaluate(&self, condition: &str) -> Result<bool> {
+        let mut any = false;
+
+        for alternative in condition.split("||") {
+            let mut all = true;
+
+            for term in alternative.split("&&") {
+                let term = term.trim();
+                let (negated, term) = match term.strip_prefix('!') {
+                    Some(term) => (true, term.trim_start()),
+                    None => (false, term),
+                };
+
+                let name = match term.strip_prefix('$') {
+                    Some(name) if !name.is_empty() => name,
+                    _ => {
+                        return Err(Error::new(ErrorKind::Message(format!(
+                            "invalid conditional [{condition}]"
+                        ))))
+                    }
+                };
+
+                all &= self.contains(name) != negated;
+            }
+
+            any |= all;
+        }
+
+        Ok(any)
+    }
+}
+
+impl Default for Platforms {
+    fn default() -> Self {
+        Self::current()
+    }
+}
@@ -0,0 +1,77 @@
+//! (De)serializes a sequence as a key that is repeated once per element.
+//!
+//! Text vdf allows the same key to appear more than once within an object,
+//! e.g. `"apps"` blocks that the Steam client merges when reading them.
+//! Use this module on a sequence field to collect every occurrence of its key:
+//!
+//! ```
+//! # use serde::Deserialize;
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     #[serde(default, with = "hbc::steam::text_vdf::repeated")]
+//!     path: Vec<String>,
+//! }
+//!
+//! let config: Config = hbc::steam::text_vdf::from_str(r#""path" "a" "path" "b""#).unwrap();
+//!
+//! assert_eq!(config.path, ["a", "b"]);
+//! ```
+//!
+//! A single occurrence yields a single element, and without `#[serde(default)]`
+//! a missing key is an error like for any other field.
+//! When serializing, each element is written under the same key,
+//! and an empty sequence writes nothing at all.
+//!
+//! Other formats see a plain sequence.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::value::SeqAccessDeserializer;
+use serde::de::{SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub(super) const REPEATED_TOKEN: &str = "$text_vdf::Repeated";
+
+pub fn serialize<T, S>(values: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + Serialize,
+    S: Serializer,
+{
+    serializer.serialize_newtype_struct(REPEATED_TOKEN, values)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_newtype_struct(REPEATED_TOKEN, RepeatedVisitor(PhantomData))
+}
+
+struct RepeatedVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for RepeatedVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a repeated key")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<T, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        T::deserialize(SeqAccessDeserializer::new(seq))
+    }
+}
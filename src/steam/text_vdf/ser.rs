@@ -0,0 +1,638 @@
+use std::io::Write;
+use std::{mem, str};
+
+use serde::ser::{self, Impossible};
+use serde::Serialize;
+
+use super::error::{Error, Result};
+use super::repeated::REPEATED_TOKEN;
+use super::TokenSerializer;
+
+/// Serializes a value, which must serialize to an object,
+/// with Steam's formatting, see `TokenSerializer`.
+pub fn to_writer<S: Serialize, W: Write>(writer: W, value: &S) -> Result<()> {
+    let mut ser = Serializer {
+        token_serializer: TokenSerializer::new(writer),
+        repeated: false,
+    };
+
+    value.serialize(&mut ser)
+}
+
+pub fn to_string<S: Serialize>(value: &S) -> Result<String> {
+    let mut bytes = Vec::new();
+
+    to_writer(&mut bytes, value)?;
+
+    // Only ever valid UTF-8 is written
+    Ok(String::from_utf8(bytes).expect("text vdf is valid UTF-8"))
+}
+
+struct Serializer<W> {
+    token_serializer: TokenSerializer<W>,
+    // Whether the next sequence is written as a repeated key, see `repeated`
+    repeated: bool,
+}
+
+impl<W> Serializer<W>
+where
+    W: Write,
+{
+    fn emit_object_start(&mut self) -> Result<()> {
+        // Any sequence within the object would be mistaken for the repeated one
+        if mem::take(&mut self.repeated) {
+            return Err(ser::Error::custom("only sequences can be repeated"));
+        }
+
+        self.token_serializer.emit_object_start()
+    }
+}
+
+impl<'a, W> ser::Serializer for &'a mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = SerializeSeq<'a, W>;
+    type SerializeTuple = SerializeSeq<'a, W>;
+    type SerializeTupleStruct = SerializeSeq<'a, W>;
+    type SerializeTupleVariant = SerializeSeq<'a, W>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.serialize_u8(value as u8)
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<()> {
+        self.serialize_str(&value.to_string())
+    }
+
+    fn serialize_char(self, value: char) -> Result<()> {
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.token_serializer.emit_string(value)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        match str::from_utf8(value) {
+            Ok(value) => self.serialize_str(value),
+            Err(_) => Err(ser::Error::custom("text vdf strings must be valid UTF-8")),
+        }
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.token_serializer.emit_none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(ser::Error::custom("serializing unit is not supported"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(ser::Error::custom(
+            "serializing unit structs is not supported",
+        ))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == REPEATED_TOKEN {
+            self.repeated = true;
+        }
+
+        value.serialize(&mut *self)?;
+
+        // Values other than sequences are written as they are
+        self.repeated = false;
+
+        Ok(())
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.emit_object_start()?;
+        self.token_serializer.emit_key(variant.to_string())?;
+
+        value.serialize(&mut *self)?;
+
+        self.token_serializer.emit_object_end()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        if mem::take(&mut self.repeated) {
+            let key = match self.token_serializer.current_key() {
+                Some(key) => key.to_string(),
+                None => return Err(ser::Error::custom("repeated values must belong to a key")),
+            };
+
+            return Ok(SerializeSeq {
+                i: 0,
+                ser: self,
+                variant: false,
+                repeated_key: Some(key),
+            });
+        }
+
+        self.emit_object_start()?;
+
+        Ok(SerializeSeq {
+            i: 0,
+            ser: self,
+            variant: false,
+            repeated_key: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.emit_object_start()?;
+        self.token_serializer.emit_key(variant.to_string())?;
+        self.emit_object_start()?;
+
+        Ok(SerializeSeq {
+            i: 0,
+            ser: self,
+            variant: true,
+            repeated_key: None,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.emit_object_start()?;
+
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.emit_object_start()?;
+
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.emit_object_start()?;
+        self.token_serializer.emit_key(variant.to_string())?;
+        self.emit_object_start()?;
+
+        Ok(self)
+    }
+}
+
+impl<W> ser::SerializeMap for &mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = key.serialize(KeySerializer)?;
+
+        self.token_serializer.emit_key(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.token_serializer.emit_object_end()
+    }
+}
+
+impl<W> ser::SerializeStruct for &mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.token_serializer.emit_key(key.to_string())?;
+
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.token_serializer.emit_object_end()
+    }
+}
+
+impl<W> ser::SerializeStructVariant for &mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.token_serializer.emit_object_end()?;
+        self.token_serializer.emit_object_end()
+    }
+}
+
+struct SerializeSeq<'a, W> {
+    ser: &'a mut Serializer<W>,
+    i: usize,
+    // Whether the sequence is wrapped in an object keyed by the variant name
+    variant: bool,
+    // Set when the elements are written as entries of the enclosing object,
+    // all under this key, instead of as an object of their own
+    repeated_key: Option<String>,
+}
+
+impl<'a, W> ser::SerializeSeq for SerializeSeq<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match &self.repeated_key {
+            // The first element is written under the key that is already pending
+            Some(_) if self.i == 0 => {}
+            Some(key) => self.ser.token_serializer.emit_key(key.clone())?,
+            None => self.ser.token_serializer.emit_key(self.i.to_string())?,
+        }
+
+        value.serialize(&mut *self.ser)?;
+
+        self.i += 1;
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        if self.repeated_key.is_some() {
+            // Without any elements, the key is not written at all
+            if self.i == 0 {
+                self.ser.token_serializer.emit_none()?;
+            }
+
+            return Ok(());
+        }
+
+        self.ser.token_serializer.emit_object_end()?;
+
+        if self.variant {
+            self.ser.token_serializer.emit_object_end()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, W> ser::SerializeTuple for SerializeSeq<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, W> ser::SerializeTupleStruct for SerializeSeq<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, W> ser::SerializeTupleVariant for SerializeSeq<'a, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Turns map keys into strings, the same way their values would be written.
+struct KeySerializer;
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, value: bool) -> Result<String> {
+        self.serialize_u8(value as u8)
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_char(self, value: char) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<String> {
+        match str::from_utf8(value) {
+            Ok(value) => Ok(value.to_string()),
+            Err(_) => Err(ser::Error::custom("text vdf keys must be valid UTF-8")),
+        }
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(unsupported_key())
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(unsupported_key())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(unsupported_key())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(unsupported_key())
+    }
+}
+
+fn unsupported_key() -> Error {
+    ser::Error::custom("unsupported key type")
+}
@@ -0,0 +1,771 @@
+use std::borrow::Cow;
+use std::fs;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use super::token_deserializer::Token;
+use super::{
+    from_str, from_token_deserializer, to_string, Document, ErrorKind, Loader, Object, Platforms,
+    TokenDeserializer, Value,
+};
+use crate::steam::id::AppId;
+
+const LIBRARY_FOLDERS: &str = r#""libraryfolders"
+{
+	"0"
+	{
+		"path"		"C:\\Program Files (x86)\\Steam"
+		"label"		""
+		"contentid"		"5148036823531475441"
+		"totalsize"		"0"
+		"apps"
+		{
+			"228980"		"311891373"
+			"440"		"24488283917"
+		}
+	}
+	"1"
+	{
+		"path"		"D:\\SteamLibrary"
+		"label"		"Games \"and\" more"
+		"contentid"		"1337"
+		"totalsize"		"1000204886016"
+		"apps"
+		{
+		}
+	}
+}
+"#;
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct LibraryFoldersVdf {
+    libraryfolders: Vec<LibraryFolder>,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct LibraryFolder {
+    path: String,
+    label: String,
+    contentid: u64,
+    totalsize: u64,
+    apps: IndexMap<AppId, u64>,
+}
+
+#[test]
+fn round_trip_library_folders() {
+    let library_folders = from_str::<LibraryFoldersVdf>(LIBRARY_FOLDERS).unwrap();
+
+    assert_eq!(
+        library_folders.libraryfolders[0].path,
+        r"C:\Program Files (x86)\Steam"
+    );
+    assert_eq!(
+        library_folders.libraryfolders[1].label,
+        r#"Games "and" more"#
+    );
+    assert_eq!(
+        library_folders.libraryfolders[0].apps[&AppId::from(440)],
+        24488283917
+    );
+    assert_eq!(to_string(&library_folders).unwrap(), LIBRARY_FOLDERS);
+}
+
+#[test]
+fn round_trip_preserves_key_order() {
+    let value = from_str::<Value>(LIBRARY_FOLDERS).unwrap();
+    let keys = value["libraryfolders"]["0"]["apps"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .collect::<Vec<_>>();
+
+    assert_eq!(keys, ["228980", "440"]);
+    assert_eq!(to_string(&value).unwrap(), LIBRARY_FOLDERS);
+}
+
+#[test]
+fn comments_and_unquoted_tokens() {
+    let input = r#"
+        // A comment before the root
+        UserLocalConfigStore {
+            friends{"offline" 1}// A comment after a brace
+            // A comment between entries
+            url "http://example.com" "x"y
+            empty ""
+        }
+    "#;
+
+    let value = from_str::<Value>(input).unwrap();
+    let store = &value["UserLocalConfigStore"];
+
+    assert_eq!(store["friends"]["offline"], Value::from("1"));
+    assert_eq!(store["url"], Value::from("http://example.com"));
+    assert_eq!(store["x"], Value::from("y"));
+    assert_eq!(store["empty"], Value::from(""));
+}
+
+#[test]
+fn escape_sequences() {
+    let value = from_str::<Value>(r#""a" "1\n2\t3\\4\"5\q""#).unwrap();
+
+    assert_eq!(value["a"], Value::from("1\n2\t3\\4\"5\\q"));
+    assert_eq!(
+        to_string(&value).unwrap(),
+        "\"a\"\t\t\"1\\n2\\t3\\\\4\\\"5\\\\q\"\n"
+    );
+}
+
+#[test]
+fn borrows_from_input() {
+    #[derive(Deserialize)]
+    struct Entry<'a> {
+        #[serde(borrow)]
+        plain: Cow<'a, str>,
+        #[serde(borrow)]
+        escaped: Cow<'a, str>,
+    }
+
+    let entry = from_str::<Entry>(r#""plain" "foo" "escaped" "\"foo\"""#).unwrap();
+
+    assert!(matches!(entry.plain, Cow::Borrowed("foo")));
+    assert!(matches!(entry.escaped, Cow::Owned(_)));
+}
+
+#[test]
+fn tokens() {
+    let mut tokens = TokenDeserializer::new(LIBRARY_FOLDERS);
+    let mut keys = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        match token.unwrap() {
+            Token::Key(key) if key == "apps" => tokens.skip_value().unwrap(),
+            Token::Key(key) => keys.push(key),
+            _ => {}
+        }
+    }
+
+    assert_eq!(
+        keys,
+        [
+            "libraryfolders",
+            "0",
+            "path",
+            "label",
+            "contentid",
+            "totalsize",
+            "1",
+            "path",
+            "label",
+            "contentid",
+            "totalsize"
+        ]
+    );
+    assert_eq!(tokens.depth(), 0);
+}
+
+#[test]
+fn types() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Kind {
+        Unit,
+        Newtype(u32),
+        Struct { a: bool },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Types {
+        int: i32,
+        float: f32,
+        bool: bool,
+        char: char,
+        none: Option<u32>,
+        some: Option<u32>,
+        tuple: (u8, String),
+        kinds: Vec<Kind>,
+        map: IndexMap<String, String>,
+    }
+
+    let types = Types {
+        int: -1,
+        float: 0.5,
+        bool: true,
+        char: 'x',
+        none: None,
+        some: Some(2),
+        tuple: (3, "4".into()),
+        kinds: vec![Kind::Unit, Kind::Newtype(5), Kind::Struct { a: false }],
+        map: IndexMap::from([("b".into(), "6".into()), ("a".into(), "7".into())]),
+    };
+
+    let string = to_string(&types).unwrap();
+
+    assert_eq!(
+        string,
+        r#""int"		"-1"
+"float"		"0.5"
+"bool"		"1"
+"char"		"x"
+"some"		"2"
+"tuple"
+{
+	"0"		"3"
+	"1"		"4"
+}
+"kinds"
+{
+	"0"		"Unit"
+	"1"
+	{
+		"Newtype"		"5"
+	}
+	"2"
+	{
+		"Struct"
+		{
+			"a"		"0"
+		}
+	}
+}
+"map"
+{
+	"b"		"6"
+	"a"		"7"
+}
+"#
+    );
+    assert_eq!(from_str::<Types>(&string).unwrap(), types);
+}
+
+#[test]
+fn root_must_be_an_object() {
+    assert!(to_string(&"foo").is_err());
+    assert!(to_string(&vec!["foo"]).is_ok());
+}
+
+#[test]
+fn error_reports_path_and_offset() {
+    #[allow(dead_code)]
+    #[derive(Debug, Deserialize)]
+    struct LibraryFolders {
+        libraryfolders: Vec<IndexMap<String, Value>>,
+    }
+
+    let err = from_str::<LibraryFolders>(r#""libraryfolders" { "0" "foo" }"#).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedToken { .. }));
+    assert_eq!(err.path(), Some("libraryfolders.0"));
+    assert_eq!(err.offset(), Some(23));
+
+    let err = from_str::<Value>(r#""a" { "b" "c""#).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    assert_eq!(err.path(), Some("a"));
+
+    let err = from_str::<Value>(r#""a" "b" }"#).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedToken { .. }));
+    assert_eq!(err.offset(), Some(8));
+
+    let err = from_str::<Value>(r#""a" "b"#).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn depth_limit() {
+    let input = "a {".repeat(1000);
+
+    let err = from_str::<Value>(&input).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::LimitExceeded { .. }));
+}
+
+#[test]
+fn repeated_keys() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Config {
+        #[serde(with = "crate::steam::text_vdf::repeated")]
+        apps: Vec<Apps>,
+        other: String,
+        #[serde(default, with = "crate::steam::text_vdf::repeated")]
+        missing: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Apps {
+        #[serde(default, with = "crate::steam::text_vdf::repeated")]
+        id: Vec<u32>,
+    }
+
+    let input = r#"
+        "apps" { "id" "1" "id" "2" }
+        "other" "x"
+        "apps" { }
+        "apps" { "id" "3" }
+    "#;
+
+    let config = from_str::<Config>(input).unwrap();
+
+    assert_eq!(
+        config,
+        Config {
+            apps: vec![
+                Apps { id: vec![1, 2] },
+                Apps { id: vec![] },
+                Apps { id: vec![3] },
+            ],
+            other: "x".into(),
+            missing: vec![],
+        }
+    );
+    assert_eq!(
+        to_string(&config).unwrap(),
+        r#""apps"
+{
+	"id"		"1"
+	"id"		"2"
+}
+"apps"
+{
+}
+"apps"
+{
+	"id"		"3"
+}
+"other"		"x"
+"#
+    );
+
+    let err = from_str::<Config>(r#""apps" { } "other" "x" "apps" { "id" "a" }"#).unwrap_err();
+
+    assert_eq!(err.path(), Some("apps.id"));
+    assert_eq!(err.offset(), Some(37));
+}
+
+#[test]
+fn interleaved_repeated_keys() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        #[serde(with = "crate::steam::text_vdf::repeated")]
+        a: Vec<String>,
+        #[serde(with = "crate::steam::text_vdf::repeated")]
+        b: Vec<String>,
+        c: String,
+    }
+
+    let input = r#""b" "1" "a" "2" "c" "x" "b" "3" "a" "4" "a" "5""#;
+
+    assert_eq!(
+        from_str::<Config>(input).unwrap(),
+        Config {
+            a: vec!["2".into(), "4".into(), "5".into()],
+            b: vec!["1".into(), "3".into()],
+            c: "x".into(),
+        }
+    );
+}
+
+#[test]
+fn repeated_keys_in_value() {
+    let input = r#""a"		"1"
+"b"		"2"
+"a"
+{
+	"c"		"3"
+}
+"#;
+
+    let mut value = from_str::<Value>(input).unwrap();
+    let object = value.as_object_mut().unwrap();
+
+    assert_eq!(object.len(), 3);
+    assert_eq!(object["a"], Value::from("1"));
+    assert_eq!(object.get_all("a").count(), 2);
+    assert_eq!(to_string(&object).unwrap(), input);
+
+    object.insert("a", Value::from("4"));
+
+    assert_eq!(object.keys().collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(object["a"], Value::from("4"));
+
+    object.append("b", Value::from("5"));
+
+    assert_eq!(object.remove_all("b"), [Value::from("2"), Value::from("5")]);
+    assert_eq!(object, &Object::from_iter([("a", Value::from("4"))]));
+}
+
+#[test]
+fn conditionals() {
+    let input = r#"
+        "a" "windows" [$WIN32]
+        "a" "posix" [$POSIX]
+        "b" [!$X360]
+        {
+            "c" "1" [$LINUX && !$DECK]
+            "d" "2"
+        }
+        "e" { "f" "3" } [$OSX || $X360]
+        "g" "4"
+    "#;
+
+    let linux = Loader::new().platforms(Platforms::none().with("LINUX").with("$posix"));
+    let value = from_token_deserializer::<Value>(linux.token_deserializer(input)).unwrap();
+
+    assert_eq!(
+        to_string(&value).unwrap(),
+        r#""a"		"posix"
+"b"
+{
+	"c"		"1"
+	"d"		"2"
+}
+"g"		"4"
+"#
+    );
+
+    let deck = Loader::new().platforms(Platforms::none().with("LINUX").with("DECK"));
+    let value = from_token_deserializer::<Value>(deck.token_deserializer(input)).unwrap();
+
+    assert_eq!(value.get("a"), None);
+    assert_eq!(
+        value["b"].as_object().unwrap().keys().collect::<Vec<_>>(),
+        ["d"]
+    );
+
+    let err = from_str::<Value>(r#""a" "b" [WIN32]"#).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::Message(_)));
+    assert_eq!(err.path(), Some("a"));
+}
+
+#[test]
+fn nested_conditionals() {
+    let input = r#"
+        "a"
+        {
+            "b" { "c" "1" } [$X360]
+            "d" { "e" { "f" "2" } [!$X360] } [$WIN32]
+            "g" "3"
+        } [!$X360]
+        "h" { "i" "4" } [$X360]
+    "#;
+
+    let windows = Loader::new().platforms(Platforms::none().with("WIN32"));
+    let mut tokens = windows.token_deserializer(input);
+    let mut paths = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        if let Token::Key(_) = token.unwrap() {
+            paths.push(tokens.path().join("."));
+        }
+    }
+
+    assert_eq!(paths, ["a", "a.d", "a.d.e", "a.d.e.f", "a.g"]);
+    assert_eq!(tokens.depth(), 0);
+
+    // Errors within an object are reported even if its conditional does not hold
+    let err = from_str::<Value>(r#""a" { "b" { "c" } } [$X360]"#).unwrap_err();
+
+    assert_eq!(err.path(), Some("a.b.c"));
+    assert_eq!(err.offset(), Some(16));
+}
+
+#[test]
+fn loader_directives() {
+    let dir = std::env::temp_dir().join(format!("hbc-text-vdf-loader-{}", std::process::id()));
+    let write = |file: &str, contents: &str| {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    };
+
+    write(
+        "main.res",
+        r#"
+            #base "base.res"
+            "Resource"
+            {
+                "a" "main"
+                "nested" { "x" "1" }
+            }
+            #include "sub\\extra.res"
+        "#,
+    );
+    write(
+        "base.res",
+        r#""Resource" { "a" "base" "b" "base" "nested" { "y" "2" } }"#,
+    );
+    write("sub/extra.res", r#""Extra" { "c" "3" [$X360] "d" "4" }"#);
+    write("cycle.res", r#"#include "sub/cycle.res""#);
+    write("sub/cycle.res", r#"#base "../cycle.res""#);
+
+    let loader = Loader::new().platforms(Platforms::none());
+    let value = loader.load::<Value>(dir.join("main.res")).unwrap();
+
+    assert_eq!(
+        to_string(&value).unwrap(),
+        r#""Resource"
+{
+	"a"		"main"
+	"nested"
+	{
+		"x"		"1"
+		"y"		"2"
+	}
+	"b"		"base"
+}
+"Extra"
+{
+	"d"		"4"
+}
+"#
+    );
+
+    let value = loader
+        .load_str::<Value>(r#"#include "sub/extra.res""#, &dir)
+        .unwrap();
+
+    assert_eq!(value["Extra"]["d"], Value::from("4"));
+
+    let err = loader.load::<Value>(dir.join("cycle.res")).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::IncludeCycle(_)));
+    assert_eq!(err.file(), Some(dir.join("sub/../cycle.res").as_path()));
+
+    let err = loader.load::<Value>(dir.join("missing.res")).unwrap_err();
+
+    assert!(matches!(err.kind(), ErrorKind::Io(_)));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+const LOCAL_CONFIG: &str = r#""UserLocalConfigStore"
+{
+	// Launch options are stored per app
+	"Software"
+	{
+		"Valve"
+		{
+			"Steam"
+			{
+				"apps"
+				{
+					"440"
+					{
+						"LaunchOptions"		"-novid"
+						"cloud"
+						{
+							"last_sync_state"		"synchronized"
+						}
+					}
+				}
+			}
+		}
+	}
+	friends { offline 1 } // Written by hand
+	"streaming"		"1" [$WIN32]
+}
+"#;
+
+#[test]
+fn document_round_trip() {
+    for input in [
+        LIBRARY_FOLDERS,
+        LOCAL_CONFIG,
+        "",
+        "// Just a comment",
+        "a b\r\nc{}",
+    ] {
+        let document = input.parse::<Document>().unwrap();
+
+        assert_eq!(document.to_string(), input);
+    }
+
+    let document = Document::parse(LIBRARY_FOLDERS).unwrap();
+
+    assert_eq!(
+        document.to_value(),
+        from_str::<Value>(LIBRARY_FOLDERS).unwrap()
+    );
+    assert_eq!(
+        document.get(&["libraryfolders", "1", "label"]),
+        Some(r#"Games "and" more"#)
+    );
+    assert_eq!(document.get(&["libraryfolders", "1"]), None);
+}
+
+#[test]
+fn document_edits() {
+    const APPS: &[&str] = &["UserLocalConfigStore", "Software", "Valve", "Steam", "apps"];
+
+    let mut document = Document::parse(LOCAL_CONFIG).unwrap();
+    let path = |keys: &[&'static str]| [APPS, keys].concat();
+
+    document
+        .set(&path(&["440", "LaunchOptions"]), "-novid -high")
+        .unwrap();
+    document
+        .set(&path(&["570", "LaunchOptions"]), "-console")
+        .unwrap();
+    document
+        .set(&["UserLocalConfigStore", "friends", "offline"], "0")
+        .unwrap();
+    document
+        .insert(&["UserLocalConfigStore", "friends", "offline"], "a b")
+        .unwrap();
+
+    assert_eq!(
+        document.remove(&path(&["440", "cloud"])),
+        Some(Value::Object(Object::from_iter([(
+            "last_sync_state",
+            Value::from("synchronized")
+        )])))
+    );
+    assert_eq!(document.remove(&path(&["440", "cloud"])), None);
+    assert!(document
+        .set(&path(&["440", "LaunchOptions", "x"]), "y")
+        .is_err());
+
+    assert_eq!(
+        document.to_string(),
+        r#""UserLocalConfigStore"
+{
+	// Launch options are stored per app
+	"Software"
+	{
+		"Valve"
+		{
+			"Steam"
+			{
+				"apps"
+				{
+					"440"
+					{
+						"LaunchOptions"		"-novid -high"
+					}
+					"570"
+					{
+						"LaunchOptions"		"-console"
+					}
+				}
+			}
+		}
+	}
+	friends { offline 0 "offline" "a b" } // Written by hand
+	"streaming"		"1" [$WIN32]
+}
+"#
+    );
+}
+
+#[test]
+fn document_remove_with_comments() {
+    let mut document = Document::parse(
+        "\"root\"\r\n{\r\n\t// About a\r\n\t\"a\" \"1\" // Note on a\r\n\
+         \t\"b\" \"2\" [$WIN32] // Note on b\r\n\t\"c\" { } // Note on c\r\n}\r\n",
+    )
+    .unwrap();
+
+    assert_eq!(document.remove(&["root", "a"]), Some(Value::from("1")));
+    assert_eq!(
+        document.to_string(),
+        "\"root\"\r\n{\r\n\t\"b\" \"2\" [$WIN32] // Note on b\r\n\t\"c\" { } // Note on c\r\n}\r\n"
+    );
+
+    document.remove(&["root", "b"]);
+    document.remove(&["root", "c"]);
+
+    assert_eq!(document.to_string(), "\"root\"\r\n{\r\n}\r\n");
+}
+
+#[test]
+fn document_line_endings() {
+    let mut document = Document::parse("\"root\"\r\n{\r\n\t\"x\"\t\t\"1\"\r\n}\r\n").unwrap();
+
+    document.set(&["root", "y"], "2").unwrap();
+    document.set(&["root", "z", "a"], "3").unwrap();
+    document.set(&["other", "b"], "4").unwrap();
+
+    assert_eq!(
+        document.to_string(),
+        "\"root\"\r\n{\r\n\t\"x\"\t\t\"1\"\r\n\t\"y\"\t\t\"2\"\r\n\t\"z\"\r\n\t{\r\n\t\t\"a\"\t\t\"3\"\r\n\t}\r\n}\r\n\
+         \"other\"\r\n{\r\n\t\"b\"\t\t\"4\"\r\n}\r\n"
+    );
+}
+
+#[test]
+fn error_spans() {
+    #[derive(Debug, Deserialize)]
+    struct Outer {
+        #[allow(dead_code)]
+        a: Inner,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Inner {
+        #[allow(dead_code)]
+        b: u32,
+    }
+
+    let input = "\"a\"\r\n{\r\n\t\"b\"\t\t\"x\"\r\n}\r\n";
+    let err = from_str::<Outer>(input).unwrap_err();
+
+    assert_eq!(err.span(), Some(14..17));
+    assert_eq!((err.line(), err.column()), (Some(3), Some(7)));
+    assert_eq!(
+        err.to_string(),
+        "invalid value: string \"x\", expected a u32 at `a.b` (line 3, column 7)"
+    );
+    assert_eq!(
+        err.render(input),
+        "error: invalid value: string \"x\", expected a u32 at `a.b`
+ --> line 3, column 7
+  |
+3 | \t\"b\"\t\t\"x\"
+  | \t   \t\t^^^"
+    );
+
+    let input = "\"a\"\n{\n\t\"ä\"\t\t\"unterminated\n}\n";
+    let err = from_str::<Value>(input).unwrap_err();
+
+    assert_eq!(err.span(), Some(13..input.len()));
+    assert_eq!(
+        err.render(input),
+        "error: unexpected end of input at `a.ä`
+ --> line 3, column 7
+  |
+3 | \t\"ä\"\t\t\"unterminated
+  | \t   \t\t^^^^^^^^^^^^^"
+    );
+
+    let err = Document::parse("\n\n}").unwrap_err();
+
+    assert_eq!(
+        err.render("\n\n}"),
+        "error: expected key, but got ObjectEnd
+ --> line 3, column 1
+  |
+3 | }
+  | ^"
+    );
+
+    // Errors at the end of input after a bare `\r`
+    #[derive(Debug, Deserialize)]
+    struct Missing {
+        #[allow(dead_code)]
+        c: String,
+    }
+
+    let input = "\"a\" \"b\"\r";
+    let err = from_str::<Missing>(input).unwrap_err();
+
+    assert_eq!(
+        err.render(input),
+        "error: missing field `c`
+ --> line 1, column 9
+  |
+1 | \"a\" \"b\"
+  |         ^"
+    );
+}
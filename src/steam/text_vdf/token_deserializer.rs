@@ -0,0 +1,520 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use super::error::{Error, ErrorKind, Result};
+use super::{Platforms, MAX_DEPTH};
+
+/// A pull parser for text vdf, yielding the document as a flat stream of `Token`s.
+///
+/// Like in binary vdf, the top level entries of a document are
+/// wrapped in an implicit root object, so the stream always starts
+/// with a `Token::ObjectStart` and ends with a `Token::ObjectEnd`.
+///
+/// Entries followed by a conditional such as `[$WIN32]` that does not hold
+/// for the configured platforms are skipped as if they were absent.
+/// As the conditional of an object only follows its closing brace,
+/// the tokens of an object are read ahead up to there before any of them are handed out.
+///
+/// Cloning is cheap and allows looking ahead without consuming any tokens.
+#[derive(Clone)]
+pub struct TokenDeserializer<'de> {
+    lexer: Lexer<'de>,
+    // The depth and next operation as of where the lexer is
+    lexer_depth: usize,
+    lexer_next_op: NextOp,
+    // Tokens that have been read ahead, and how many of them were handed out
+    read_ahead: Arc<[ReadToken<'de>]>,
+    handed_out: usize,
+    // The state as of the most recent token that was handed out
+    position: usize,
+    token_offset: usize,
+    token_end: usize,
+    next_op: NextOp,
+    depth: usize,
+    path: Vec<String>,
+    platforms: Platforms,
+}
+
+/// A token that was read ahead, along with the state after it.
+#[derive(Clone)]
+struct ReadToken<'de> {
+    token: Token<'de>,
+    position: usize,
+    token_offset: usize,
+    token_end: usize,
+    next_op: NextOp,
+    depth: usize,
+    // The length of the path before the token, which is pushed to the path if it is a key
+    path_len: usize,
+}
+
+impl<'de> TokenDeserializer<'de> {
+    pub fn new(input: &'de str) -> Self {
+        Self {
+            lexer: Lexer::new(input),
+            lexer_depth: 0,
+            lexer_next_op: NextOp::ParseValue,
+            read_ahead: Arc::new([]),
+            handed_out: 0,
+            position: 0,
+            token_offset: 0,
+            token_end: 0,
+            next_op: NextOp::ParseValue,
+            depth: 0,
+            path: Vec::new(),
+            platforms: Platforms::current(),
+        }
+    }
+
+    /// The platforms to evaluate conditionals against, `Platforms::current()` by default.
+    pub fn with_platforms(mut self, platforms: Platforms) -> Self {
+        self.platforms = platforms;
+        self
+    }
+
+    /// The number of objects enclosing the next token, including the root object.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The keys leading to the most recent token.
+    ///
+    /// After a `Token::Key` this ends with that key, and it keeps doing
+    /// so for the following value, up to and including its `Token::ObjectEnd`.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// The number of bytes read up to the end of the most recent token.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Attaches the location of the most recent token to `err`.
+    pub(super) fn locate(&self, err: Error) -> Error {
+        err.at(
+            self.token_offset..self.token_end,
+            self.path(),
+            self.lexer.input,
+        )
+    }
+
+    fn start_token(&mut self) {
+        self.token_offset = self.lexer.position;
+        self.token_end = self.lexer.position;
+    }
+
+    /// Skips over the value of the most recent `Token::Key`,
+    /// including everything within it if it is an object.
+    /// Called before the first token, this skips the whole document.
+    pub fn skip_value(&mut self) -> Result<()> {
+        if self.next_op != NextOp::ParseValue {
+            return Err(self.locate(Error::new(ErrorKind::Message(
+                "skip_value must follow a key".to_string(),
+            ))));
+        }
+
+        let depth = self.depth;
+
+        while let Some(token) = self.next() {
+            token?;
+
+            if self.depth == depth && self.next_op != NextOp::ParseValue {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads tokens ahead up to the end of the entry they belong to,
+    /// at which point its conditionals are known, and drops
+    /// the tokens of entries whose conditionals do not hold.
+    ///
+    /// Entries that fail to parse are kept, so that the tokens before
+    /// the error are handed out and the error is reported at the right token.
+    fn fill(&mut self) -> Result<()> {
+        let mut tokens = Vec::new();
+        // The index of the key of each object entry whose closing brace is yet to come,
+        // and whether the conditional before its value holds
+        let mut open = Vec::new();
+
+        while self.lexer_next_op != NextOp::DoNothing {
+            let (lexer, depth, next_op) = (self.lexer, self.lexer_depth, self.lexer_next_op);
+            let path_len = self.lexer_path_len();
+
+            if let Err(err) = self.read_token_ahead(&mut tokens, &mut open) {
+                if tokens.is_empty() {
+                    self.path.truncate(path_len);
+                    self.position = self.lexer.position;
+                    return Err(err);
+                }
+
+                // The tokens before the error are handed out first,
+                // and the error recurs when reading on from here
+                self.lexer = lexer;
+                self.lexer_depth = depth;
+                self.lexer_next_op = next_op;
+                break;
+            }
+
+            if open.is_empty() && self.lexer_next_op != NextOp::ParseValue && !tokens.is_empty() {
+                break;
+            }
+        }
+
+        self.read_ahead = tokens.into();
+        self.handed_out = 0;
+
+        Ok(())
+    }
+
+    fn lexer_path_len(&self) -> usize {
+        match self.lexer_next_op {
+            NextOp::ParseKey => self.lexer_depth - 1,
+            NextOp::ParseValue | NextOp::DoNothing => self.lexer_depth,
+        }
+    }
+
+    fn read_token_ahead(
+        &mut self,
+        tokens: &mut Vec<ReadToken<'de>>,
+        open: &mut Vec<(usize, bool)>,
+    ) -> Result<()> {
+        let path_len = self.lexer_path_len();
+
+        let token = match self.lexer_next_op {
+            NextOp::ParseKey => self.handle_parse_key(tokens, open)?,
+            NextOp::ParseValue => self.handle_parse_value(tokens, open)?,
+            NextOp::DoNothing => None,
+        };
+
+        if let Some(token) = token {
+            tokens.push(ReadToken {
+                token,
+                position: self.lexer.position,
+                token_offset: self.token_offset,
+                token_end: self.token_end,
+                next_op: self.lexer_next_op,
+                depth: self.lexer_depth,
+                path_len,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn handle_parse_key(
+        &mut self,
+        tokens: &mut Vec<ReadToken<'de>>,
+        open: &mut Vec<(usize, bool)>,
+    ) -> Result<Option<Token<'de>>> {
+        self.lexer.skip_whitespace_and_comments();
+        self.start_token();
+
+        match self.lexer.peek() {
+            None if self.lexer_depth == 1 => {
+                self.lexer_depth -= 1;
+                self.lexer_next_op = NextOp::DoNothing;
+
+                Ok(Some(Token::ObjectEnd))
+            }
+            None => Err(Error::new(ErrorKind::UnexpectedEof)),
+            Some(b'}') if self.lexer_depth > 1 => {
+                self.lexer.position += 1;
+                self.lexer_depth -= 1;
+                self.token_end = self.lexer.position;
+
+                let holds = self.read_conditional()?;
+
+                match open.pop() {
+                    Some((key, before)) if !(before && holds) => {
+                        tokens.truncate(key);
+
+                        Ok(None)
+                    }
+                    _ => Ok(Some(Token::ObjectEnd)),
+                }
+            }
+            Some(b'{' | b'}') => {
+                let token = self.lexer.read_token()?;
+                Err(Error::unexpected_token("key", token.into_owned()))
+            }
+            Some(_) => {
+                let key = self.lexer.read_string()?;
+                self.token_end = self.lexer.position;
+                self.lexer_next_op = NextOp::ParseValue;
+
+                Ok(Some(Token::Key(key)))
+            }
+        }
+    }
+
+    fn handle_parse_value(
+        &mut self,
+        tokens: &mut Vec<ReadToken<'de>>,
+        open: &mut Vec<(usize, bool)>,
+    ) -> Result<Option<Token<'de>>> {
+        // The root object has no braces
+        if self.lexer_depth == 0 {
+            self.lexer_depth += 1;
+            self.lexer_next_op = NextOp::ParseKey;
+
+            return Ok(Some(Token::ObjectStart));
+        }
+
+        // The key of this entry is the most recent token
+        let key = tokens.len().saturating_sub(1);
+        let holds = self.read_conditional()?;
+
+        self.lexer.skip_whitespace_and_comments();
+        self.start_token();
+
+        let token = self.lexer.read_token()?;
+        self.token_end = self.lexer.position;
+
+        match token {
+            Token::ObjectStart => {
+                if self.lexer_depth >= MAX_DEPTH {
+                    return Err(Error::limit_exceeded("max_depth", MAX_DEPTH));
+                }
+
+                self.lexer_depth += 1;
+                self.lexer_next_op = NextOp::ParseKey;
+                // Whether the entry is kept is known at the closing brace
+                open.push((key, holds));
+
+                Ok(Some(Token::ObjectStart))
+            }
+            Token::String(value) => {
+                let holds = self.read_conditional()? && holds;
+                self.lexer_next_op = NextOp::ParseKey;
+
+                if !holds {
+                    tokens.truncate(key);
+                    return Ok(None);
+                }
+
+                Ok(Some(Token::String(value)))
+            }
+            token => Err(Error::unexpected_token("value", token.into_owned())),
+        }
+    }
+
+    /// Reads a conditional such as `[$WIN32]`, if there is one,
+    /// and returns whether it holds.
+    fn read_conditional(&mut self) -> Result<bool> {
+        self.lexer.skip_whitespace_and_comments();
+
+        match self.lexer.read_condition()? {
+            Some(condition) => self.platforms.evaluate(condition),
+            None => Ok(true),
+        }
+    }
+}
+
+impl<'de> Iterator for TokenDeserializer<'de> {
+    type Item = Result<Token<'de>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.handed_out == self.read_ahead.len() {
+            if let Err(err) = self.fill() {
+                self.next_op = NextOp::DoNothing;
+                self.lexer_next_op = NextOp::DoNothing;
+                // Span the input read so far, up to where it went wrong
+                self.token_end = self.token_end.max(self.lexer.position);
+                return Some(Err(self.locate(err)));
+            }
+        }
+
+        let read = self.read_ahead.get(self.handed_out)?.clone();
+        self.handed_out += 1;
+
+        self.position = read.position;
+        self.token_offset = read.token_offset;
+        self.token_end = read.token_end;
+        self.next_op = read.next_op;
+        self.depth = read.depth;
+        self.path.truncate(read.path_len);
+
+        if let Token::Key(key) = &read.token {
+            self.path.push(key.to_string());
+        }
+
+        Some(Ok(read.token))
+    }
+}
+
+/// Reads the pieces that text vdf is made of, without any notion of its structure.
+#[derive(Copy, Clone)]
+pub(super) struct Lexer<'de> {
+    input: &'de str,
+    position: usize,
+}
+
+impl<'de> Lexer<'de> {
+    pub(super) fn new(input: &'de str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    /// The number of bytes read so far.
+    pub(super) fn position(&self) -> usize {
+        self.position
+    }
+
+    pub(super) fn read_condition(&mut self) -> Result<Option<&'de str>> {
+        if self.peek() != Some(b'[') {
+            return Ok(None);
+        }
+
+        let rest = &self.input[self.position + 1..];
+
+        let end = match rest.find(']') {
+            Some(end) => end,
+            None => {
+                self.position = self.input.len();
+                return Err(Error::new(ErrorKind::UnexpectedEof));
+            }
+        };
+
+        self.position += end + 2;
+
+        Ok(Some(&rest[..end]))
+    }
+
+    /// Reads a brace or a string, quoted or not.
+    pub(super) fn read_token(&mut self) -> Result<Token<'de>> {
+        match self.peek() {
+            None => Err(Error::new(ErrorKind::UnexpectedEof)),
+            Some(b'{') => {
+                self.position += 1;
+                Ok(Token::ObjectStart)
+            }
+            Some(b'}') => {
+                self.position += 1;
+                Ok(Token::ObjectEnd)
+            }
+            Some(_) => self.read_string().map(Token::String),
+        }
+    }
+
+    pub(super) fn read_string(&mut self) -> Result<Cow<'de, str>> {
+        if self.peek() == Some(b'"') {
+            self.position += 1;
+            return self.read_quoted_string();
+        }
+
+        let rest = &self.input[self.position..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '{' | '}'))
+            .unwrap_or(rest.len());
+
+        self.position += len;
+
+        Ok(Cow::Borrowed(&rest[..len]))
+    }
+
+    fn read_quoted_string(&mut self) -> Result<Cow<'de, str>> {
+        let rest = &self.input[self.position..];
+
+        let end = match rest.find(['"', '\\']) {
+            Some(end) => end,
+            None => return Err(self.unterminated_string()),
+        };
+
+        // Strings without escape sequences are borrowed as they are
+        if rest.as_bytes()[end] == b'"' {
+            self.position += end + 1;
+            return Ok(Cow::Borrowed(&rest[..end]));
+        }
+
+        let mut string = String::with_capacity(end);
+        let mut chars = rest.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += i + 1;
+                    return Ok(Cow::Owned(string));
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => unescape(escaped, &mut string),
+                    None => break,
+                },
+                c => string.push(c),
+            }
+        }
+
+        Err(self.unterminated_string())
+    }
+
+    fn unterminated_string(&mut self) -> Error {
+        self.position = self.input.len();
+        Error::new(ErrorKind::UnexpectedEof)
+    }
+
+    pub(super) fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            let rest = &self.input[self.position..];
+            let trimmed = rest.trim_start();
+
+            self.position += rest.len() - trimmed.len();
+
+            if !trimmed.starts_with("//") {
+                return;
+            }
+
+            self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    pub(super) fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+}
+
+/// Resolves the character following a backslash.
+///
+/// Unknown escape sequences are kept as they are,
+/// so that unescaped Windows paths survive.
+fn unescape(escaped: char, string: &mut String) {
+    match escaped {
+        'n' => string.push('\n'),
+        't' => string.push('\t'),
+        'r' => string.push('\r'),
+        '\\' | '"' => string.push(escaped),
+        _ => {
+            string.push('\\');
+            string.push(escaped);
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum NextOp {
+    DoNothing,
+    ParseValue,
+    ParseKey,
+}
+
+/// A single piece of text vdf input.
+///
+/// Keys and strings borrow from the input unless they contain escape sequences.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token<'a> {
+    ObjectStart,
+    ObjectEnd,
+    Key(Cow<'a, str>),
+    String(Cow<'a, str>),
+}
+
+impl Token<'_> {
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::ObjectStart => Token::ObjectStart,
+            Token::ObjectEnd => Token::ObjectEnd,
+            Token::Key(key) => Token::Key(Cow::Owned(key.into_owned())),
+            Token::String(value) => Token::String(Cow::Owned(value.into_owned())),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::io::Write;
+
+use serde::ser;
+
+use super::error::Result;
+
+/// Writes text vdf the way Steam does: every key and value quoted,
+/// two tabs between a key and its value, and braces on lines
+/// of their own, indented by one tab per level of nesting.
+pub struct TokenSerializer<W> {
+    writer: W,
+    expected: Expected,
+    current_key: Option<String>,
+    depth: usize,
+}
+
+impl<W> TokenSerializer<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            expected: Expected::Value,
+            current_key: None,
+            depth: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub fn emit_object_start(&mut self) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        // The root object has no braces
+        if self.depth > 0 {
+            let key = self.take_current_key()?;
+
+            self.write_indent()?;
+            self.write_string(&key)?;
+            self.writer.write_all(b"\n")?;
+            self.write_indent()?;
+            self.writer.write_all(b"{\n")?;
+        }
+
+        self.depth += 1;
+
+        self.expected = Expected::KeyOrEndObject;
+
+        Ok(())
+    }
+
+    pub fn emit_key(&mut self, key: String) -> Result<()> {
+        self.assert(Expected::KeyOrEndObject)?;
+
+        self.current_key = Some(key);
+
+        self.expected = Expected::Value;
+
+        Ok(())
+    }
+
+    /// The key awaiting its value, if any.
+    pub fn current_key(&self) -> Option<&str> {
+        self.current_key.as_deref()
+    }
+
+    pub fn emit_object_end(&mut self) -> Result<()> {
+        self.assert(Expected::KeyOrEndObject)?;
+
+        self.depth -= 1;
+
+        if self.depth > 0 {
+            self.write_indent()?;
+            self.writer.write_all(b"}\n")?;
+        }
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    pub fn emit_string(&mut self, string: &str) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        let key = self.take_current_key()?;
+
+        self.write_indent()?;
+        self.write_string(&key)?;
+        self.writer.write_all(b"\t\t")?;
+        self.write_string(string)?;
+        self.writer.write_all(b"\n")?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    /// Drops the pending key, so that an absent value leaves no trace.
+    pub fn emit_none(&mut self) -> Result<()> {
+        self.assert(Expected::Value)?;
+
+        self.take_current_key()?;
+
+        self.end_of_value();
+
+        Ok(())
+    }
+
+    fn end_of_value(&mut self) {
+        if self.depth == 0 {
+            self.expected = Expected::End;
+        } else {
+            self.expected = Expected::KeyOrEndObject;
+        }
+    }
+
+    fn assert(&self, got: Expected) -> Result<()> {
+        if self.expected != got {
+            return Err(ser::Error::custom(format!(
+                "Expected {:?}, but got {:?}",
+                self.expected, got
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn take_current_key(&mut self) -> Result<String> {
+        match self.current_key.take() {
+            Some(key) => Ok(key),
+            None => Err(ser::Error::custom("text vdf root must be an object")),
+        }
+    }
+
+    fn write_indent(&mut self) -> Result<()> {
+        // Entries of the root object are not indented
+        for _ in 1..self.depth {
+            self.writer.write_all(b"\t")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_string(&mut self, value: &str) -> Result<()> {
+        self.writer.write_all(b"\"")?;
+        self.writer.write_all(escape(value).as_bytes())?;
+        self.writer.write_all(b"\"")?;
+
+        Ok(())
+    }
+}
+
+/// Escapes a string to be written between quotes.
+pub(super) fn escape(value: &str) -> Cow<'_, str> {
+    if !value.contains(['\\', '"', '\n', '\t', '\r']) {
+        return Cow::Borrowed(value);
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 2);
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Expected {
+    Value,
+    KeyOrEndObject,
+    End,
+}